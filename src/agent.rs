@@ -4,18 +4,22 @@ use std::sync::Arc;
 
 use hoot::BodyMode;
 use http::uri::Scheme;
-use http::{HeaderName, HeaderValue, Method, Request, Response, Uri};
+use http::{header, HeaderName, HeaderValue, Method, Request, Response, Uri};
 
 use crate::body::{Body, ResponseInfo};
 use crate::middleware::MiddlewareNext;
+use crate::partial::PartialRequest;
 use crate::pool::{Connection, ConnectionPool};
-use crate::resolver::{DefaultResolver, Resolver};
-use crate::send_body::AsSendBody;
-use crate::transport::time::Instant;
+use crate::proxy::ProxyUnreachable;
+use crate::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+use crate::response::{ConnectionReused, Elapsed, NoBody, RequestUri};
+use crate::send_body::{AsSendBody, BodySent, SentBody};
+use crate::single_flight::{request_key, SingleFlight};
+use crate::transport::time::{Duration, Instant, NextTimeout};
 use crate::transport::{ConnectionDetails, Connector, DefaultConnector, NoBuffers};
 use crate::unit::{Event, Input, Unit};
 use crate::util::{DebugResponse, HeaderMapExt, UriExt};
-use crate::{AgentConfig, Error, RequestBuilder, SendBody, Timeouts};
+use crate::{AgentConfig, Error, RequestBuilder, SendBody, TimeoutReason, Timeouts};
 use crate::{WithBody, WithoutBody};
 
 /// Agents keep state between requests.
@@ -48,6 +52,7 @@ pub struct Agent {
     config: Arc<AgentConfig>,
     pool: Arc<ConnectionPool>,
     resolver: Arc<dyn Resolver>,
+    single_flight: Arc<SingleFlight>,
 
     #[cfg(feature = "cookies")]
     jar: Arc<crate::cookies::SharedCookieJar>,
@@ -86,12 +91,88 @@ impl Agent {
             config: Arc::new(config),
             pool,
             resolver: Arc::new(resolver),
+            single_flight: Arc::new(SingleFlight::default()),
 
             #[cfg(feature = "cookies")]
             jar: Arc::new(crate::cookies::SharedCookieJar::new()),
         }
     }
 
+    /// Creates an agent with temporary config changes, sharing state with this agent.
+    ///
+    /// The scoped agent uses the same connection pool, resolver and cookie jar, but
+    /// requests made from it use the modified config. The settings of the connection
+    /// pool itself, such as [`AgentConfig::max_idle_connections`], stay as for this agent.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let agent = ureq::agent();
+    ///
+    /// let impatient = agent.scoped(|config| {
+    ///     config.timeouts.global = Some(Duration::from_secs(1));
+    /// });
+    ///
+    /// impatient.get("http://httpbin.org/get").call()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn scoped(&self, f: impl FnOnce(&mut AgentConfig)) -> Agent {
+        let mut config = AgentConfig::clone(&self.config);
+        f(&mut config);
+
+        Agent {
+            config: Arc::new(config),
+            ..self.clone()
+        }
+    }
+
+    /// Opens connections to a host ahead of the first request.
+    ///
+    /// The `count` connections are opened, including any TLS handshake, and parked in
+    /// the connection pool. Following requests to the same scheme, host and port use
+    /// them rather than connecting, which takes connection setup out of the latency of
+    /// the first requests. Only the path-less part of the `uri` matters.
+    ///
+    /// The pool keeps at most [`AgentConfig::max_idle_connections_per_host`] of them.
+    /// Uses the resolve and connect timeouts of the agent.
+    ///
+    /// ```no_run
+    /// let agent = ureq::agent();
+    ///
+    /// agent.warmup("https://httpbin.org", 2)?;
+    ///
+    /// // Uses one of the already open connections.
+    /// agent.get("https://httpbin.org/get").call()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn warmup<T>(&self, uri: T, count: usize) -> Result<(), Error>
+    where
+        Uri: TryFrom<T>,
+        <Uri as TryFrom<T>>::Error: Into<http::Error>,
+    {
+        let uri = Uri::try_from(uri).map_err(|e| Error::Http(e.into()))?;
+        let timeouts = &self.config.timeouts;
+
+        let timeout = |v: Option<std::time::Duration>, reason| NextTimeout {
+            after: v.map(Duration::from).unwrap_or(Duration::NotHappening),
+            reason,
+        };
+
+        let details = ConnectionDetails {
+            uri: &uri,
+            addrs: self.resolve(&uri, timeout(timeouts.resolve, TimeoutReason::Resolver))?,
+            resolver: &*self.resolver,
+            config: &self.config,
+            now: Instant::now(),
+            timeout: timeout(timeouts.connect, TimeoutReason::OpenConnection),
+        };
+
+        // Warming up doesn't go direct, so report the error as is.
+        self.pool
+            .warmup(&details, count)
+            .map_err(|e| ProxyUnreachable::unwrap(e).unwrap_or_else(|e| e))
+    }
+
     /// Access the cookie jar.
     ///
     /// Used to persist and manipulate the cookies.
@@ -140,10 +221,54 @@ impl Agent {
         &self,
         request: Request<()>,
         body: SendBody,
+    ) -> Result<Response<Body>, Error> {
+        let partial = PartialRequest::new(&request);
+
+        let response = match self.config.single_flight {
+            Some(max)
+                if request.method() == Method::GET && body.body_mode() == BodyMode::NoBody =>
+            {
+                let key = request_key(request.uri(), request.headers());
+                self.single_flight
+                    .run(key, max as u64, || self.do_run_unit(request, body))?
+            }
+            _ => self.do_run_unit(request, body)?,
+        };
+
+        match partial {
+            Some(partial) => partial.complete(self, response),
+            None => Ok(response),
+        }
+    }
+
+    fn do_run_unit(
+        &self,
+        mut request: Request<()>,
+        body: SendBody,
     ) -> Result<Response<Body>, Error> {
         // TODO(martin): use this in tests to try timeouts etc.
         let current_time = Instant::now;
 
+        // A manually set content-length that doesn't match a body of known size makes
+        // for a malformed request. The actual body size wins.
+        if let BodyMode::LengthDelimited(len) = body.body_mode() {
+            let headers = request.headers_mut();
+            if headers.contains_key(header::CONTENT_LENGTH) && headers.content_length() != Some(len)
+            {
+                debug!("Correct content-length header to body size: {}", len);
+                headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+            }
+        }
+
+        // Several host headers make for a malformed request. The last one set wins.
+        let hosts = request.headers().get_all(header::HOST);
+        if hosts.iter().count() > 1 {
+            // unwrap is ok because of count above
+            let host = hosts.iter().next_back().unwrap().clone();
+            debug!("Keep last of several host headers: {:?}", host);
+            request.headers_mut().insert(header::HOST, host);
+        }
+
         let headers = request.headers();
         let send_body_mode = if headers.has_send_body_mode() {
             None
@@ -160,12 +285,23 @@ impl Agent {
             .get::<Timeouts>()
             .unwrap_or(&self.config().timeouts);
 
-        let mut unit = Unit::new(self.config.clone(), timeouts, current_time(), request, body)?;
+        let sent_body = self
+            .config
+            .retain_request_body
+            .and_then(|max| body.as_slice().filter(|v| v.len() <= max))
+            .map(|v| SentBody(v.to_vec()));
+
+        let start = current_time();
+        let mut unit = Unit::new(self.config.clone(), timeouts, start, request, body)?;
 
         // For CONNECT proxy, this is the address of the proxy server, for
         // all other cases it's the address of the URL being requested.
         let mut addrs = None;
 
+        // Set if the CONNECT proxy can't be resolved and we go direct instead. Only
+        // for the current hop, a redirect tries the proxy again.
+        let mut direct = false;
+
         let mut connection: Option<Connection> = None;
         let mut response;
         let mut no_buffers = NoBuffers;
@@ -182,6 +318,7 @@ impl Agent {
             match unit.poll_event(current_time(), buffers)? {
                 Event::Reset { must_close } => {
                     addrs = None;
+                    direct = false;
 
                     if let Some(c) = connection.take() {
                         if must_close {
@@ -234,6 +371,8 @@ impl Agent {
                         }
                     }
 
+                    // hoot doesn't follow a redirect that would send the body again.
+                    let send_body_mode = send_body_mode.filter(|_| !unit.is_redirect());
                     if let Some(send_body_mode) = send_body_mode {
                         match send_body_mode {
                             BodyMode::LengthDelimited(v) => {
@@ -259,22 +398,24 @@ impl Agent {
                 }
 
                 Event::Resolve { uri, timeout } => {
-                    // If we're using a CONNECT proxy, we need to resolve that hostname.
-                    let maybe_connect_uri = self.config.connect_proxy_uri();
-
-                    let effective_uri = maybe_connect_uri.unwrap_or(uri);
-
-                    // Before resolving the URI we need to ensure it is a full URI. We
-                    // cannot make requests with partial uri like "/path".
-                    effective_uri.ensure_valid_url()?;
-
-                    addrs = Some(
-                        self.resolver
-                            .resolve(effective_uri, &self.config, timeout)?,
-                    );
+                    match self.resolve(uri, timeout) {
+                        Ok(v) => addrs = Some(v),
+                        Err(e @ (Error::HostNotFound | Error::Io(_) | Error::Timeout(_)))
+                            if self.falls_back_direct() =>
+                        {
+                            debug!("Proxy not resolved, connect directly: {}", e);
+                            direct = true;
+                        }
+                        Err(e) => return Err(e),
+                    }
                     unit.handle_input(current_time(), Input::Resolved, &mut [])?;
                 }
 
+                Event::OpenConnection { uri, timeout } if direct => {
+                    connection = Some(self.connect_direct(uri, timeout)?);
+                    unit.handle_input(current_time(), Input::ConnectionOpen, &mut [])?;
+                }
+
                 Event::OpenConnection { uri, timeout } => {
                     let addrs = addrs
                         .take()
@@ -288,7 +429,17 @@ impl Agent {
                         now: current_time(),
                         timeout,
                     };
-                    connection = Some(self.pool.connect(&details)?);
+
+                    connection = Some(match self.pool.connect(&details) {
+                        Ok(v) => v,
+                        Err(e) => match ProxyUnreachable::unwrap(e) {
+                            Ok(e) => {
+                                debug!("Proxy connection failed, connect directly: {}", e);
+                                self.connect_direct(uri, details.remaining_timeout()?)?
+                            }
+                            Err(e) => return Err(e),
+                        },
+                    });
 
                     unit.handle_input(current_time(), Input::ConnectionOpen, &mut [])?;
 
@@ -384,6 +535,9 @@ impl Agent {
 
         let response = response.expect("above loop to exit when there is a response");
         let connection = connection.expect("connection to be open");
+        let elapsed = Elapsed(*current_time().duration_since(start));
+        let body_sent = BodySent(unit.body_sent());
+        let request_uri = RequestUri(unit.current_uri().clone());
         let unit = unit.release_body();
         let status = response.status();
         let is_err = status.is_client_error() || status.is_server_error();
@@ -392,8 +546,24 @@ impl Agent {
             return Err(Error::StatusCode(status.as_u16()));
         }
 
-        let (parts, _) = response.into_parts();
-        let info = ResponseInfo::new(&parts.headers, recv_body_mode);
+        let (mut parts, _) = response.into_parts();
+        parts.extensions.insert(body_sent);
+        parts.extensions.insert(elapsed);
+        parts.extensions.insert(request_uri);
+        if recv_body_mode == BodyMode::NoBody {
+            parts.extensions.insert(NoBody);
+        }
+        if connection.is_reused() {
+            parts.extensions.insert(ConnectionReused);
+        }
+        if let Some(sent_body) = sent_body {
+            parts.extensions.insert(sent_body);
+        }
+        let info = ResponseInfo::new(
+            &parts.headers,
+            recv_body_mode,
+            self.config.max_decompressed_size,
+        );
         let recv_body = Body::new(unit, connection, info, current_time);
         let response = Response::from_parts(parts, recv_body);
 
@@ -403,6 +573,43 @@ impl Agent {
         Ok(response)
     }
 
+    /// Resolves the host to connect to for the uri, which is the proxy if there is one.
+    fn resolve(&self, uri: &Uri, timeout: NextTimeout) -> Result<ResolvedSocketAddrs, Error> {
+        // If we're using a CONNECT proxy, we need to resolve that hostname.
+        let maybe_connect_uri = self.config.connect_proxy_uri();
+
+        let effective_uri = maybe_connect_uri.unwrap_or(uri);
+
+        // Before resolving the URI we need to ensure it is a full URI. We
+        // cannot make requests with partial uri like "/path".
+        effective_uri.ensure_valid_url()?;
+
+        self.resolver.resolve(effective_uri, &self.config, timeout)
+    }
+
+    /// Whether failing to reach the proxy means connecting without it.
+    fn falls_back_direct(&self) -> bool {
+        self.config.proxy_fallback_direct && self.config.connect_proxy_uri().is_some()
+    }
+
+    fn connect_direct(&self, uri: &Uri, timeout: NextTimeout) -> Result<Connection, Error> {
+        let config = AgentConfig {
+            proxy: None,
+            ..(*self.config).clone()
+        };
+
+        let details = ConnectionDetails {
+            uri,
+            addrs: self.resolver.resolve(uri, &config, timeout)?,
+            resolver: &*self.resolver,
+            config: &config,
+            now: Instant::now(),
+            timeout,
+        };
+
+        self.pool.connect(&details)
+    }
+
     pub(crate) fn config(&self) -> &AgentConfig {
         &self.config
     }
@@ -453,6 +660,7 @@ impl From<AgentConfig> for Agent {
 
 #[cfg(test)]
 impl crate::Agent {
+    /// Number of idle connections in the pool.
     pub fn pool_count(&self) -> usize {
         self.pool.pool_count()
     }
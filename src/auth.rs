@@ -0,0 +1,226 @@
+//! Parsing of `WWW-Authenticate` challenges.
+
+use crate::util::parse_quoted;
+
+/// An authentication challenge from a `WWW-Authenticate` header.
+///
+/// Obtained via [`ResponseExt::auth_challenges()`](crate::ResponseExt::auth_challenges).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthChallenge {
+    scheme: String,
+    token68: Option<String>,
+    params: Vec<(String, String)>,
+}
+
+impl AuthChallenge {
+    /// The authentication scheme, such as `Basic` or `Digest`.
+    ///
+    /// Schemes are case insensitive, and this is as sent by the server.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// The value of a parameter, such as `realm` or `nonce`.
+    ///
+    /// Parameter names are matched case insensitively. Quoted values are unquoted.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All parameters as name and value, in the order sent.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// The single token some schemes use instead of parameters.
+    pub fn token68(&self) -> Option<&str> {
+        self.token68.as_deref()
+    }
+}
+
+/// Parses the challenges of one `WWW-Authenticate` header value as per RFC 7235.
+///
+/// A header can hold several comma separated challenges. Unparseable parts are skipped.
+pub(crate) fn parse_challenges(value: &str, out: &mut Vec<AuthChallenge>) {
+    let mut c = Cursor { s: value, pos: 0 };
+
+    loop {
+        c.skip_ws_and_commas();
+
+        if c.at_end() {
+            break;
+        }
+
+        let Some(scheme) = c.token() else {
+            // Not a challenge, skip to the next comma.
+            debug!("Skip bad auth challenge: {}", value);
+            while !c.at_end() && c.peek() != Some(b',') {
+                c.pos += 1;
+            }
+            continue;
+        };
+
+        let mut challenge = AuthChallenge {
+            scheme: scheme.to_string(),
+            token68: None,
+            params: vec![],
+        };
+
+        c.skip_ws();
+
+        let before = c.pos;
+        if let Some(token68) = c.token68() {
+            c.skip_ws();
+            if c.at_end() || c.peek() == Some(b',') {
+                challenge.token68 = Some(token68.to_string());
+                out.push(challenge);
+                continue;
+            }
+        }
+        c.pos = before;
+
+        while let Some((name, value)) = c.param() {
+            challenge.params.push((name.to_string(), value));
+        }
+
+        out.push(challenge);
+    }
+}
+
+struct Cursor<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.pos).copied()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.s.len()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_ws_and_commas(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b',')) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> Option<&'a str> {
+        let start = self.pos;
+        while self.peek().map(&f).unwrap_or(false) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| &self.s[start..self.pos])
+    }
+
+    fn token(&mut self) -> Option<&'a str> {
+        self.take_while(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+    }
+
+    fn token68(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        self.take_while(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b))?;
+        while self.peek() == Some(b'=') {
+            self.pos += 1;
+        }
+        Some(&self.s[start..self.pos])
+    }
+
+    /// A quoted string, with the quotes removed and escapes resolved.
+    fn quoted(&mut self) -> Option<String> {
+        let (value, len) = parse_quoted(&self.s[self.pos..])?;
+        self.pos += len;
+        Some(value)
+    }
+
+    /// A `name=value` parameter followed by an optional comma.
+    ///
+    /// Leaves the cursor in place if there is none, such as when the next challenge starts.
+    fn param(&mut self) -> Option<(&'a str, String)> {
+        let start = self.pos;
+
+        let maybe_param = (|| {
+            self.skip_ws_and_commas();
+            let name = self.token()?;
+            self.skip_ws();
+            if self.peek() != Some(b'=') {
+                return None;
+            }
+            self.pos += 1;
+            self.skip_ws();
+            let value = if self.peek() == Some(b'"') {
+                self.quoted()?
+            } else {
+                self.token()?.to_string()
+            };
+            self.skip_ws();
+            Some((name, value))
+        })();
+
+        if maybe_param.is_none() {
+            self.pos = start;
+        }
+
+        maybe_param
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(v: &str) -> Vec<AuthChallenge> {
+        let mut out = vec![];
+        parse_challenges(v, &mut out);
+        out
+    }
+
+    #[test]
+    fn digest_challenge() {
+        let c = parse(
+            r#"Digest realm="test@example.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e", opaque="5ccc069c""#,
+        );
+
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].scheme(), "Digest");
+        assert_eq!(c[0].param("realm"), Some("test@example.com"));
+        assert_eq!(c[0].param("QOP"), Some("auth,auth-int"));
+        assert_eq!(c[0].param("nonce"), Some("dcd98b7102dd2f0e"));
+        assert_eq!(c[0].params().count(), 4);
+    }
+
+    #[test]
+    fn multiple_challenges() {
+        let c = parse(
+            r#"Newauth realm="apps", type=1, title="Login to \"apps\"", Basic realm="simple""#,
+        );
+
+        assert_eq!(c.len(), 2);
+        assert_eq!(c[0].scheme(), "Newauth");
+        assert_eq!(c[0].param("type"), Some("1"));
+        assert_eq!(c[0].param("title"), Some(r#"Login to "apps""#));
+        assert_eq!(c[1].scheme(), "Basic");
+        assert_eq!(c[1].param("realm"), Some("simple"));
+    }
+
+    #[test]
+    fn token68_and_bare_schemes() {
+        let c = parse("Negotiate, Bearer abc+/def==, Basic");
+
+        let schemes: Vec<_> = c.iter().map(|c| c.scheme()).collect();
+        assert_eq!(schemes, ["Negotiate", "Bearer", "Basic"]);
+        assert_eq!(c[1].token68(), Some("abc+/def=="));
+        assert_eq!(c[0].params().count(), 0);
+    }
+}
@@ -19,3 +19,43 @@ impl<R: io::Read> io::Read for BrotliDecoder<R> {
             .map_err(|e| Error::Decompress("brotli", e).into_io())
     }
 }
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use crate::test::init_test_log;
+    use crate::transport::set_handler;
+
+    // "hello world" as a single uncompressed meta-block followed by an empty last one.
+    const BR_HELLO: &[u8] = &[
+        0xA0, 0x00, 0x10, b'h', b'e', b'l', b'l', b'o', b' ', b'w', b'o', b'r', b'l', b'd', 0x03,
+    ];
+
+    #[test]
+    fn brotli_chunked() {
+        init_test_log();
+
+        let mut body = format!("{:x}\r\n", BR_HELLO.len()).into_bytes();
+        body.extend_from_slice(BR_HELLO);
+        body.extend_from_slice(b"\r\n0\r\n\r\n");
+
+        set_handler(
+            "/br_chunked",
+            200,
+            &[("transfer-encoding", "chunked"), ("content-encoding", "br")],
+            &body,
+        );
+
+        let mut res = crate::get("https://my.test/br_chunked").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello world");
+    }
+
+    #[test]
+    fn brotli_no_content() {
+        init_test_log();
+
+        set_handler("/br_204", 204, &[("content-encoding", "br")], &[]);
+
+        let mut res = crate::get("https://my.test/br_204").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "");
+    }
+}
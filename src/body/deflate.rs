@@ -0,0 +1,214 @@
+use std::io::{self, Chain, Cursor, Read};
+use std::mem;
+
+use flate2::read::{DeflateDecoder as RawDecoder, ZlibDecoder};
+
+use crate::Error;
+
+/// Decoder for `content-encoding: deflate`.
+///
+/// The spec says deflate means zlib-wrapped DEFLATE, but plenty of servers send raw
+/// DEFLATE. We try zlib first, and if that fails before producing any output, start
+/// over with raw DEFLATE on the same input.
+pub(crate) struct DeflateDecoder<R>(Inner<R>);
+
+enum Inner<R> {
+    Zlib(ZlibDecoder<Recorder<R>>),
+    Raw(RawDecoder<Chain<Cursor<Vec<u8>>, R>>),
+    // Only while moving the reader from Zlib to Raw.
+    Switching,
+}
+
+/// Keeps a copy of the input until we know it's zlib, to start over with raw DEFLATE.
+struct Recorder<R> {
+    reader: R,
+    recorded: Option<Vec<u8>>,
+    // The last read of the underlying reader failed. Such errors are not the decoding
+    // failing, and must not make us fall back to raw.
+    failed: bool,
+}
+
+impl<R: io::Read> DeflateDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        let recorder = Recorder {
+            reader,
+            recorded: Some(Vec::new()),
+            failed: false,
+        };
+        DeflateDecoder(Inner::Zlib(ZlibDecoder::new(recorder)))
+    }
+
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Inner::Zlib(zlib) = &mut self.0 {
+            zlib.get_mut().failed = false;
+            let result = zlib.read(buf);
+            let recorder = zlib.get_mut();
+
+            if recorder.recorded.is_none() {
+                return result;
+            }
+
+            match result {
+                Ok(n) => {
+                    trace!("Deflate is zlib-wrapped");
+                    recorder.recorded = None;
+                    return Ok(n);
+                }
+                // Try zlib again on the next read, the recorded input is kept.
+                Err(e) if recorder.failed => return Err(e),
+                Err(e) => {
+                    trace!("Deflate is not zlib-wrapped ({}), try raw", e);
+                    self.switch_to_raw();
+                }
+            }
+        }
+
+        match &mut self.0 {
+            Inner::Raw(raw) => raw.read(buf),
+            Inner::Zlib(_) | Inner::Switching => unreachable!("switch_to_raw() to leave Raw"),
+        }
+    }
+
+    fn switch_to_raw(&mut self) {
+        let Inner::Zlib(zlib) = mem::replace(&mut self.0, Inner::Switching) else {
+            unreachable!("switch_to_raw() only from Zlib");
+        };
+
+        // The decoder's own buffer is dropped, but all it read is in the recording.
+        let recorder = zlib.into_inner();
+        let recorded = recorder.recorded.unwrap_or_default();
+        let replayed = Cursor::new(recorded).chain(recorder.reader);
+
+        self.0 = Inner::Raw(RawDecoder::new(replayed));
+    }
+}
+
+impl<R: io::Read> io::Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf).map_err(|e| {
+            self.failed = true;
+            e
+        })?;
+        if let Some(recorded) = &mut self.recorded {
+            recorded.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: io::Read> io::Read for DeflateDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_inner(buf)
+            .map_err(|e| Error::Decompress("deflate", e).into_io())
+    }
+}
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use std::io::{self, Read, Write};
+
+    use flate2::write::{DeflateEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    use crate::test::init_test_log;
+    use crate::transport::set_handler;
+
+    use super::DeflateDecoder;
+
+    const TEXT: &str = "hello world, hello deflate";
+
+    #[test]
+    fn deflate_zlib_wrapped() {
+        init_test_log();
+
+        let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        let body = enc.finish().unwrap();
+
+        let len = body.len().to_string();
+        set_handler(
+            "/zlib",
+            200,
+            &[("content-encoding", "deflate"), ("content-length", &len)],
+            &body,
+        );
+
+        let mut res = crate::get("https://my.test/zlib").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn deflate_raw() {
+        init_test_log();
+
+        let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        let body = enc.finish().unwrap();
+
+        let len = body.len().to_string();
+        set_handler(
+            "/raw",
+            200,
+            &[("content-encoding", "deflate"), ("content-length", &len)],
+            &body,
+        );
+
+        let mut res = crate::get("https://my.test/raw").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), TEXT);
+    }
+
+    #[test]
+    fn deflate_raw_starting_like_zlib() {
+        init_test_log();
+
+        // A raw stored block of "h", whose first two bytes happen to be a valid zlib
+        // header, followed by a final stored block of "ello".
+        let body: &[u8] = &[
+            0x78, 0x01, 0x00, 0xfe, 0xff, b'h', //
+            0x01, 0x04, 0x00, 0xfb, 0xff, b'e', b'l', b'l', b'o',
+        ];
+
+        let mut out = String::new();
+        DeflateDecoder::new(body).read_to_string(&mut out).unwrap();
+        assert_eq!(out, "hello");
+    }
+
+    #[test]
+    fn deflate_retries_after_read_error() {
+        init_test_log();
+
+        // Fails the first read, like a read timeout would.
+        struct FailOnce<R> {
+            failed: bool,
+            reader: R,
+        }
+
+        impl<R: io::Read> io::Read for FailOnce<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if !self.failed {
+                    self.failed = true;
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                self.reader.read(buf)
+            }
+        }
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT.as_bytes()).unwrap();
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(TEXT.as_bytes()).unwrap();
+
+        for body in [zlib.finish().unwrap(), raw.finish().unwrap()] {
+            let mut dec = DeflateDecoder::new(FailOnce {
+                failed: false,
+                reader: &body[..],
+            });
+            let mut buf = [0; 64];
+            assert!(dec.read(&mut buf).is_err());
+
+            let mut out = String::new();
+            dec.read_to_string(&mut out).unwrap();
+            assert_eq!(out, TEXT);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use md5::{Digest, Md5};
+use ring::digest::{self, SHA256, SHA512};
+
+use crate::Error;
+
+type Hash = fn(&[u8]) -> Vec<u8>;
+
+/// Algorithms we verify, as named in the `Digest` header (RFC 3230).
+const ALGORITHMS: &[(&str, Hash)] = &[("sha-256", sha256), ("sha-512", sha512), ("md5", md5)];
+
+fn sha256(data: &[u8]) -> Vec<u8> {
+    digest::digest(&SHA256, data).as_ref().to_vec()
+}
+
+fn sha512(data: &[u8]) -> Vec<u8> {
+    digest::digest(&SHA512, data).as_ref().to_vec()
+}
+
+fn md5(data: &[u8]) -> Vec<u8> {
+    Md5::digest(data).to_vec()
+}
+
+/// Verify data against a `Digest` header value such as `sha-256=X48E9q...=`, and a
+/// `Content-MD5` header value (RFC 1864).
+///
+/// All entries with supported algorithms, and the `Content-MD5`, must match, and there
+/// must be at least one.
+pub(crate) fn verify(
+    header: Option<&str>,
+    content_md5: Option<&str>,
+    data: &[u8],
+) -> Result<(), Error> {
+    let mut verified = false;
+
+    for entry in header.unwrap_or_default().split(',') {
+        // The base64 value can end with =, but the algorithm name never contains one.
+        let Some((name, value)) = entry.trim().split_once('=') else {
+            continue;
+        };
+
+        let Some((name, hash)) = ALGORITHMS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))
+        else {
+            continue;
+        };
+
+        check(name, *hash, value, data)?;
+        verified = true;
+    }
+
+    if let Some(value) = content_md5 {
+        check("md5", md5, value, data)?;
+        verified = true;
+    }
+
+    if verified {
+        Ok(())
+    } else {
+        Err(Error::ContentDigest(None))
+    }
+}
+
+fn check(name: &'static str, hash: Hash, value: &str, data: &[u8]) -> Result<(), Error> {
+    let expected = STANDARD
+        .decode(value.trim())
+        .map_err(|_| Error::ContentDigest(Some(name)))?;
+
+    if hash(data) != expected {
+        return Err(Error::ContentDigest(Some(name)));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use crate::test::init_test_log;
+    use crate::transport::set_handler;
+    use crate::Error;
+
+    // sha-256 of "hello"
+    const HELLO_SHA256: &str = "sha-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+
+    #[test]
+    fn digest_matches() {
+        init_test_log();
+        set_handler(
+            "/digest_ok",
+            200,
+            &[("content-length", "5"), ("digest", HELLO_SHA256)],
+            b"hello",
+        );
+
+        let mut res = crate::get("https://my.test/digest_ok").call().unwrap();
+        assert_eq!(res.body_mut().read_to_vec_verified().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn digest_mismatch() {
+        init_test_log();
+        set_handler(
+            "/digest_bad",
+            200,
+            &[("content-length", "5"), ("digest", HELLO_SHA256)],
+            b"jello",
+        );
+
+        let mut res = crate::get("https://my.test/digest_bad").call().unwrap();
+        let err = res.body_mut().read_to_vec_verified().unwrap_err();
+        assert!(matches!(err, Error::ContentDigest(Some("sha-256"))));
+    }
+
+    #[test]
+    fn content_md5() {
+        init_test_log();
+        // md5 of "hello"
+        let headers = &[
+            ("content-length", "5"),
+            ("content-md5", "XUFAKrxLKna5cZ2REBfFkg=="),
+        ];
+        set_handler("/md5_ok", 200, headers, b"hello");
+        set_handler("/md5_bad", 200, headers, b"jello");
+
+        let mut res = crate::get("https://my.test/md5_ok").call().unwrap();
+        assert_eq!(res.body_mut().read_to_vec_verified().unwrap(), b"hello");
+
+        let mut res = crate::get("https://my.test/md5_bad").call().unwrap();
+        let err = res.body_mut().read_to_vec_verified().unwrap_err();
+        assert!(matches!(err, Error::ContentDigest(Some("md5"))));
+    }
+
+    #[test]
+    fn digest_missing() {
+        init_test_log();
+        set_handler(
+            "/digest_missing",
+            200,
+            &[("content-length", "5"), ("digest", "unknown=abc")],
+            b"hello",
+        );
+
+        let mut res = crate::get("https://my.test/digest_missing").call().unwrap();
+        let err = res.body_mut().read_to_vec_verified().unwrap_err();
+        assert!(matches!(err, Error::ContentDigest(None)));
+    }
+}
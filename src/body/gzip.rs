@@ -24,7 +24,7 @@ impl<R: io::Read> io::Read for GzipDecoder<R> {
 mod test {
     use crate::test::init_test_log;
     use crate::transport::set_handler;
-    use crate::Agent;
+    use crate::{Agent, AgentConfig, Error};
 
     // Test that a stream gets returned to the pool if it is gzip encoded and the gzip
     // decoder reads the exact amount from a chunked stream, not past the 0. This
@@ -66,4 +66,109 @@ mod test {
 
         assert_eq!(agent.pool_count(), 1);
     }
+
+    #[test]
+    fn gz_round_trip() {
+        use std::io::Write;
+
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        init_test_log();
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(b"hello world").unwrap();
+        let body = enc.finish().unwrap();
+
+        let len = body.len().to_string();
+        set_handler(
+            "/gz_text",
+            200,
+            &[("content-encoding", "gzip"), ("content-length", &len)],
+            &body,
+        );
+
+        let mut res = crate::get("https://my.test/gz_text").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello world");
+    }
+
+    #[test]
+    fn gunzip_gzip_payload() {
+        use std::io::{Read, Write};
+
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        init_test_log();
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(b"hello world").unwrap();
+        let body = enc.finish().unwrap();
+
+        let len = body.len().to_string();
+        set_handler(
+            "/file.gz",
+            200,
+            &[
+                ("content-type", "application/gzip"),
+                ("content-length", &len),
+            ],
+            &body,
+        );
+
+        // Without content-encoding, the gzip file is the payload.
+        let mut res = crate::get("https://my.test/file.gz").call().unwrap();
+        assert_eq!(res.body_mut().read_to_vec().unwrap(), body);
+
+        let res = crate::get("https://my.test/file.gz").call().unwrap();
+        let mut text = String::new();
+        res.into_body()
+            .into_reader_gunzip()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hello world");
+    }
+
+    #[test]
+    fn gz_decompression_bomb() {
+        use std::io::Write;
+
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        init_test_log();
+
+        // 1MB of zeros compresses to about 1KB.
+        let mut enc = GzEncoder::new(Vec::new(), Compression::best());
+        enc.write_all(&[0; 1024 * 1024]).unwrap();
+        let body = enc.finish().unwrap();
+
+        let len = body.len().to_string();
+        set_handler(
+            "/gz_bomb",
+            200,
+            &[("content-encoding", "gzip"), ("content-length", &len)],
+            &body,
+        );
+
+        let agent: Agent = AgentConfig {
+            max_decompressed_size: Some(64 * 1024),
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/gz_bomb").call().unwrap();
+        let err = res.body_mut().read_to_vec().unwrap_err();
+        assert!(matches!(err, Error::DecompressExceedsLimit(65536)));
+
+        // Exactly at the limit is fine.
+        let agent: Agent = AgentConfig {
+            max_decompressed_size: Some(1024 * 1024),
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/gz_bomb").call().unwrap();
+        assert_eq!(res.body_mut().read_to_vec().unwrap().len(), 1024 * 1024);
+    }
 }
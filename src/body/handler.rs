@@ -1,26 +1,136 @@
-use std::io;
+use std::io::{self, Read};
+use std::sync::Arc;
 
 use crate::pool::Connection;
 use crate::transport::time::Instant;
 use crate::unit::{Event, Input, Unit};
-use crate::Error;
+use crate::{Body, Error};
+
+/// Max number of unexpected bytes we discard after a response that must not
+/// have a body (such as for HEAD), before we give up on reusing the connection.
+const MAX_NO_BODY_DISCARD: usize = 8 * 1024;
 
 pub(crate) struct UnitHandler {
     unit: Unit<()>,
     connection: Option<Connection>,
+    no_body: bool,
     current_time: Box<dyn Fn() -> Instant + Send + Sync>,
+    /// Stop reading (cleanly, as if the body ended) at this time.
+    stop_at: Option<Instant>,
+}
+
+/// Where the body data comes from.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum BodySource {
+    /// Read from the connection, driven by the unit.
+    Unit(UnitHandler),
+    /// The entire (undecoded) body is already in memory.
+    Buffered(io::Cursor<Arc<[u8]>>),
+    /// A partial body, continued by requesting the remaining ranges.
+    Ranges(Box<RangeSource>),
+}
+
+impl BodySource {
+    /// Make the body end at the given time.
+    ///
+    /// A body that is already in memory doesn't wait for anything and is read to end.
+    pub fn set_stop_at(&mut self, stop_at: Instant) {
+        match self {
+            BodySource::Unit(v) => v.stop_at = Some(stop_at),
+            BodySource::Buffered(_) => {}
+            BodySource::Ranges(v) => {
+                v.stop_at = Some(stop_at);
+                v.current.set_stop_at(stop_at);
+            }
+        }
+    }
+
+    fn do_read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self {
+            BodySource::Unit(v) => v.do_read(buf),
+            BodySource::Buffered(v) => Ok(v.read(buf)?),
+            BodySource::Ranges(v) => v.do_read(buf),
+        }
+    }
 }
 
-pub(crate) enum UnitHandlerRef<'a> {
-    Shared(&'a mut UnitHandler),
-    Owned(UnitHandler),
+type NextRange = Box<dyn FnMut(u64) -> Result<Body, Error> + Send + Sync>;
+
+pub(crate) struct RangeSource {
+    current: BodySource,
+    offset: u64,
+    total: u64,
+    next: NextRange,
+    /// Carried over to the bodies of the remaining ranges.
+    stop_at: Option<Instant>,
 }
 
-impl<'a> UnitHandlerRef<'a> {
+impl RangeSource {
+    pub fn new(current: BodySource, total: u64, next: NextRange) -> Self {
+        RangeSource {
+            current,
+            offset: 0,
+            total,
+            next,
+            stop_at: None,
+        }
+    }
+
+    fn do_read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // Whether the current part was just requested.
+        let mut fresh = false;
+
+        loop {
+            let n = self.current.do_read(buf)?;
+
+            if n > 0 {
+                self.offset += n as u64;
+                if self.offset > self.total {
+                    return Err(Error::PartialContent("more data than the total size"));
+                }
+                return Ok(n);
+            }
+
+            if self.offset == self.total {
+                return Ok(0);
+            }
+
+            if fresh {
+                return Err(Error::PartialContent("empty range"));
+            }
+
+            if let Some(stop_at) = self.stop_at {
+                if Instant::now() >= stop_at {
+                    debug!("Stop before requesting remaining range");
+                    return Ok(0);
+                }
+            }
+
+            debug!("Request remaining range from: {}", self.offset);
+            self.current = (self.next)(self.offset)?.source;
+            if let Some(stop_at) = self.stop_at {
+                self.current.set_stop_at(stop_at);
+            }
+            fresh = true;
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum BodySourceRef<'a> {
+    Shared(&'a mut BodySource),
+    Owned(BodySource),
+}
+
+impl<'a> BodySourceRef<'a> {
     pub fn do_read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         match self {
-            UnitHandlerRef::Shared(v) => v.do_read(buf),
-            UnitHandlerRef::Owned(v) => v.do_read(buf),
+            BodySourceRef::Shared(v) => v.do_read(buf),
+            BodySourceRef::Owned(v) => v.do_read(buf),
         }
     }
 }
@@ -29,13 +139,29 @@ impl UnitHandler {
     pub fn new(
         unit: Unit<()>,
         connection: Connection,
+        no_body: bool,
         current_time: impl Fn() -> Instant + Send + Sync + 'static,
     ) -> Self {
-        Self {
+        let mut handler = Self {
             unit,
             connection: Some(connection),
+            no_body,
             current_time: Box::new(current_time),
+            stop_at: None,
+        };
+
+        // A response without body is already complete. Release the connection
+        // straight away rather than waiting for the user to read the empty body.
+        if no_body {
+            if let Err(e) = handler.do_read(&mut []) {
+                debug!(
+                    "Failed to release connection for response without body: {}",
+                    e
+                );
+            }
         }
+
+        handler
     }
 
     fn do_read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
@@ -44,6 +170,12 @@ impl UnitHandler {
         };
 
         loop {
+            if let Some(stop_at) = self.stop_at {
+                if (self.current_time)() >= stop_at {
+                    return Ok(self.stop());
+                }
+            }
+
             let has_buffered_input = connection.buffers().can_use_input();
 
             // Each read to the underlying buffers needs to be kept in sync with the
@@ -57,6 +189,8 @@ impl UnitHandler {
                         if must_close {
                             trace!("Must close");
                             connection.close()
+                        } else if self.no_body && has_buffered_input {
+                            discard_no_body_input(connection, (self.current_time)())
                         } else if has_buffered_input {
                             debug!("Close due to excess body data");
                             connection.close()
@@ -84,7 +218,21 @@ impl UnitHandler {
                 }
             }
 
-            let made_progress = connection.await_input(timeout)?;
+            // Wait no longer than until stop_at.
+            let mut timeout = timeout;
+            let mut stopping = false;
+            if let Some(stop_at) = self.stop_at {
+                let left = stop_at.duration_since((self.current_time)());
+                if *left < *timeout.after {
+                    timeout.after = left;
+                    stopping = true;
+                }
+            }
+
+            let made_progress = match connection.await_input(timeout) {
+                Err(Error::Timeout(_)) if stopping => return Ok(self.stop()),
+                r => r?,
+            };
 
             let amount = ship_input(connection, &mut self.unit, &self.current_time, buf)?;
             if amount > 0 {
@@ -95,11 +243,39 @@ impl UnitHandler {
                 // decoding any gzip.
                 continue;
             } else {
-                // This is an edge case we don't want to see.
-                return Err(Error::BodyStalled);
+                // The connection closed before the end of the body, be it a content-length
+                // or chunked body. A body delimited by the close has ended above.
+                return Err(Error::body_truncated());
             }
         }
     }
+
+    /// Ends the body early. The connection is closed since the body isn't read to end.
+    fn stop(&mut self) -> usize {
+        if let Some(connection) = self.connection.take() {
+            debug!("Stop reading body, close connection");
+            connection.close();
+        }
+        0
+    }
+}
+
+/// A response that must not have a body (HEAD, 204, 304) was followed by some bytes
+/// anyway. Discard them if they are few, otherwise the connection is beyond repair.
+fn discard_no_body_input(mut connection: Connection, now: Instant) {
+    let amount = connection.buffers().input().len();
+
+    if amount > MAX_NO_BODY_DISCARD {
+        debug!("Close due to {} bytes after response without body", amount);
+        connection.close();
+        return;
+    }
+
+    debug!("Discard {} bytes after response without body", amount);
+    connection.consume_input(amount);
+
+    // reuse() probes the transport, which closes it if even more bytes are waiting.
+    connection.reuse(now);
 }
 
 fn ship_input(
@@ -121,7 +297,7 @@ fn ship_input(
     Ok(amount)
 }
 
-impl<'a> io::Read for UnitHandlerRef<'a> {
+impl<'a> io::Read for BodySourceRef<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.do_read(buf).map_err(|e| e.into_io())
     }
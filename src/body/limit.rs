@@ -20,15 +20,61 @@ impl<R> LimitReader<R> {
 
 impl<R: io::Read> io::Read for LimitReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.left == 0 {
+        // Allow one byte over the limit, to tell a body of exactly the limit
+        // from one that is larger. The max buffer size is usize, which may be 32 bit.
+        let max = (self.left.saturating_add(1).min(usize::MAX as u64) as usize).min(buf.len());
+
+        let n = self.reader.read(&mut buf[..max])?;
+
+        if n as u64 > self.left {
             return Err(Error::BodyExceedsLimit(self.limit).into_io());
         }
 
-        // The max buffer size is usize, which may be 32 bit.
-        let max = (self.left.min(usize::MAX as u64) as usize).min(buf.len());
+        self.left -= n as u64;
+
+        Ok(n)
+    }
+}
+
+/// Limits the output of a decompressing reader.
+///
+/// Unlike [`LimitReader`], this is about the decompressed size, which can be many
+/// times the size of the (compressed) input.
+pub(crate) struct DecompressLimit<R> {
+    reader: R,
+    limit: Option<u64>,
+    left: u64,
+}
+
+impl<R> DecompressLimit<R> {
+    pub fn new(reader: R, limit: Option<u64>) -> Self {
+        DecompressLimit {
+            reader,
+            limit,
+            left: limit.unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for DecompressLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(limit) = self.limit else {
+            return self.reader.read(buf);
+        };
+
+        // Allow one byte over the limit, to tell a body of exactly the limit
+        // from one that is larger.
+        let max = (self.left.saturating_add(1).min(usize::MAX as u64) as usize).min(buf.len());
 
         let n = self.reader.read(&mut buf[..max])?;
 
+        if n as u64 > self.left {
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            return Err(Error::DecompressExceedsLimit(limit).into_io());
+            #[cfg(not(any(feature = "gzip", feature = "brotli")))]
+            unreachable!("decompress limit {} without decompression", limit);
+        }
+
         self.left -= n as u64;
 
         Ok(n)
@@ -67,4 +113,33 @@ mod test {
         println!("{:?}", err);
         assert!(matches!(err, Error::BodyExceedsLimit(3)));
     }
+
+    #[test]
+    fn reader_with_limit_on_chunked() {
+        use std::io::Read;
+
+        init_test_log();
+        set_handler(
+            "/chunked_limit",
+            200,
+            &[("transfer-encoding", "chunked")],
+            b"a\r\n0123456789\r\n0\r\n\r\n",
+        );
+
+        let read = |limit: u64| {
+            let res = crate::get("https://my.test/chunked_limit").call().unwrap();
+            let mut buf = vec![];
+            let result = res
+                .into_body()
+                .into_reader_with_limit(limit)
+                .read_to_end(&mut buf);
+            result.map(|_| buf)
+        };
+
+        assert_eq!(read(10).unwrap(), b"0123456789");
+
+        let err = read(9).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(Error::from(err), Error::BodyExceedsLimit(9)));
+    }
 }
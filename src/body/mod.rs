@@ -1,5 +1,8 @@
 use core::fmt;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
 use std::io::{self, Read};
+use std::path::Path;
 use std::sync::Arc;
 
 use hoot::BodyMode;
@@ -7,10 +10,11 @@ use hoot::BodyMode;
 use crate::pool::Connection;
 use crate::transport::time::Instant;
 use crate::unit::Unit;
+use crate::util::parse_content_type;
 use crate::Error;
 
-use self::handler::{UnitHandler, UnitHandlerRef};
-use self::limit::LimitReader;
+use self::handler::{BodySource, BodySourceRef, RangeSource, UnitHandler};
+use self::limit::{DecompressLimit, LimitReader};
 use self::lossy::LossyUtf8Reader;
 
 mod handler;
@@ -23,9 +27,15 @@ mod charset;
 #[cfg(feature = "gzip")]
 mod gzip;
 
+#[cfg(feature = "gzip")]
+mod deflate;
+
 #[cfg(feature = "brotli")]
 mod brotli;
 
+#[cfg(feature = "digest")]
+mod digest;
+
 /// Default max body size for read_to_string() and read_to_vec().
 const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
 
@@ -35,12 +45,12 @@ const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
 ///
 /// ```
 /// use std::io::Read;
+/// use ureq::ResponseExt;
+///
 /// let mut res = ureq::get("http://httpbin.org/bytes/100")
 ///     .call()?;
 ///
-/// assert!(res.headers().contains_key("Content-Length"));
-/// let len: usize = res.headers().get("Content-Length")
-///     .unwrap().to_str().unwrap().parse().unwrap();
+/// let len = res.content_length().unwrap() as usize;
 ///
 /// let mut bytes: Vec<u8> = Vec::with_capacity(len);
 /// res.body_mut().as_reader()
@@ -49,10 +59,9 @@ const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
 /// assert_eq!(bytes.len(), len);
 /// # Ok::<_, ureq::Error>(())
 /// ```
-
 pub struct Body {
     info: Arc<ResponseInfo>,
-    unit_handler: UnitHandler,
+    source: BodySource,
 }
 
 #[derive(Clone)]
@@ -61,6 +70,11 @@ pub(crate) struct ResponseInfo {
     mime_type: Option<String>,
     charset: Option<String>,
     body_mode: BodyMode,
+    max_decompressed: Option<u64>,
+    #[cfg(feature = "digest")]
+    digest: Option<String>,
+    #[cfg(feature = "digest")]
+    content_md5: Option<String>,
 }
 
 impl Body {
@@ -70,9 +84,10 @@ impl Body {
         info: ResponseInfo,
         current_time: impl Fn() -> Instant + Send + Sync + 'static,
     ) -> Self {
+        let no_body = info.body_mode == BodyMode::NoBody;
         Body {
             info: Arc::new(info),
-            unit_handler: UnitHandler::new(unit, connection, current_time),
+            source: BodySource::Unit(UnitHandler::new(unit, connection, no_body, current_time)),
         }
     }
 
@@ -139,7 +154,7 @@ impl Body {
     ///     .read_to_end(&mut bytes)?;
     /// # Ok::<_, ureq::Error>(())
     /// ```
-    pub fn as_reader(&mut self) -> BodyReader {
+    pub fn as_reader(&mut self) -> BodyReader<'_> {
         self.with_config().into_reader()
     }
 
@@ -169,6 +184,172 @@ impl Body {
         self.into_with_config().into_reader()
     }
 
+    /// Turn this response into an owned `impl Read` of the body, limited to `max` bytes.
+    ///
+    /// Reading more than `max` bytes of the body errors with [`Error::BodyExceedsLimit`],
+    /// as an [`io::Error`] of kind `InvalidData`. This protects against servers sending
+    /// endless bodies. Same as `into_with_config().limit(max).into_reader()`.
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// let mut bytes = vec![];
+    /// res.into_body()
+    ///     .into_reader_with_limit(1000)
+    ///     .read_to_end(&mut bytes)?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn into_reader_with_limit(self, max: u64) -> BodyReader<'static> {
+        self.into_with_config().limit(max).into_reader()
+    }
+
+    /// Turn this response into an owned `impl Read` of the body, that ends after a time.
+    ///
+    /// Once the `duration` has passed, the reader cleanly ends (returns EOF), rather than
+    /// erroring as for a timeout. This is useful to stop open-ended streams, such as when
+    /// tailing a log for a while. The connection is then closed, since the body isn't
+    /// read to end.
+    ///
+    /// For a partial response completed with
+    /// [`RequestBuilder::auto_complete_partial`](crate::RequestBuilder::auto_complete_partial),
+    /// this covers the requests for the remaining ranges, and no more are made once the time
+    /// is up. A body that is already in memory is read to end regardless.
+    ///
+    /// * Reader is not limited.
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    ///
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// let (_, body) = res.into_parts();
+    ///
+    /// let mut bytes: Vec<u8> = Vec::new();
+    /// body.into_reader_timed(Duration::from_secs(5))
+    ///     .read_to_end(&mut bytes)?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn into_reader_timed(mut self, duration: std::time::Duration) -> BodyReader<'static> {
+        self.source.set_stop_at(Instant::now() + duration.into());
+        self.into_reader()
+    }
+
+    /// Turn this response into an owned `impl Read` of the gunzipped body.
+    ///
+    /// This is for a body that _is_ a gzip file, such as `Content-Type: application/gzip`,
+    /// which isn't decompressed automatically. The body is decompressed regardless of the
+    /// headers. Any `Content-Encoding` is decoded first, as for [`Body::into_reader()`].
+    ///
+    /// * Reader is not limited.
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    ///
+    /// let res = ureq::get("http://httpbin.org/gzip")
+    ///     .call()?;
+    ///
+    /// let mut text = String::new();
+    /// res.into_body()
+    ///     .into_reader_gunzip()
+    ///     .read_to_string(&mut text)?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn into_reader_gunzip(self) -> impl io::Read + Send + Sync + 'static {
+        gzip::GzipDecoder::new(self.into_reader())
+    }
+
+    /// Turn this response into an owned, buffered `impl BufRead` of the body.
+    ///
+    /// Same as [`Body::into_reader()`] wrapped in a [`BufReader`](io::BufReader), which is
+    /// useful to peek at the body, or parse it line-by-line.
+    ///
+    /// * Reader is not limited. To set a limit use [`Body::into_with_config()`].
+    ///
+    /// ```
+    /// use std::io::BufRead;
+    ///
+    /// let res = ureq::get("http://httpbin.org/robots.txt")
+    ///     .call()?;
+    ///
+    /// let (_, body) = res.into_parts();
+    ///
+    /// let lines = body.into_buf_reader()
+    ///     .lines()
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    ///
+    /// assert_eq!(lines, ["User-agent: *", "Disallow: /deny"]);
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn into_buf_reader(self) -> io::BufReader<BodyReader<'static>> {
+        io::BufReader::new(self.into_reader())
+    }
+
+    /// Turn this response into an iterator over the lines of the body.
+    ///
+    /// Each line is without the trailing `\n` or `\r\n`. Lines are read as they arrive,
+    /// which suits log tailing and newline-delimited JSON. Errors, such as timeouts,
+    /// are returned as `Err` items.
+    ///
+    /// * Reader is not limited.
+    ///
+    /// ```
+    /// let res = ureq::get("http://httpbin.org/robots.txt")
+    ///     .call()?;
+    ///
+    /// for line in res.into_body().into_lines() {
+    ///     println!("{}", line?);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn into_lines(self) -> io::Lines<io::BufReader<BodyReader<'static>>> {
+        use io::BufRead;
+        self.into_buf_reader().lines()
+    }
+
+    /// Turn this response into an iterator of values from newline-delimited JSON.
+    ///
+    /// Requires the **json** feature.
+    ///
+    /// Each non-blank line is deserialized into `T`. Invalid JSON is returned as an
+    /// `Err` of kind `InvalidData`, wrapping an [`Error::Json`]. Like
+    /// [`Body::into_lines()`], the values are read as they arrive.
+    ///
+    /// * Reader is not limited.
+    ///
+    /// ```no_run
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Change {
+    ///     id: u64,
+    /// }
+    ///
+    /// let res = ureq::get("http://httpbin.org/ndjson")
+    ///     .call()?;
+    ///
+    /// for change in res.into_body().into_json_lines::<Change>() {
+    ///     println!("{}", change?.id);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn into_json_lines<T: serde::de::DeserializeOwned>(
+        self,
+    ) -> impl Iterator<Item = io::Result<T>> {
+        self.into_lines()
+            .filter(|line| !matches!(line, Ok(v) if v.trim().is_empty()))
+            .map(|line| {
+                serde_json::from_str(&line?)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, Error::Json(e)))
+            })
+    }
+
     /// Read the response as a string.
     ///
     /// * Response is limited to 10MB
@@ -211,11 +392,147 @@ impl Body {
             .read_to_vec()
     }
 
+    /// Read the response to a seekable in-memory cursor.
+    ///
+    /// * Response is limited to 10MB.
+    ///
+    /// For formats that need random access, such as ZIP archives. The whole body is
+    /// held in memory, so for large bodies consider [`Body::save_to_file()`] instead.
+    /// To change the limit use [`Body::with_config()`].
+    ///
+    /// ```
+    /// use std::io::{Seek, SeekFrom};
+    ///
+    /// let mut res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// let mut cursor = res.body_mut().read_to_seekable()?;
+    /// cursor.seek(SeekFrom::End(-10))?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn read_to_seekable(&mut self) -> Result<io::Cursor<Vec<u8>>, Error> {
+        self.with_config()
+            //
+            .limit(MAX_BODY_SIZE)
+            .read_to_seekable()
+    }
+
+    /// Read the response to a vec, verified against the `Digest` and `Content-MD5` headers.
+    ///
+    /// Requires the **digest** feature.
+    ///
+    /// * Response is limited to 10MB.
+    ///
+    /// The digest is of the body as sent, before any decompression. The `Digest` algorithms
+    /// `sha-256`, `sha-512` and `md5` are supported. Errors with [`Error::ContentDigest`] if
+    /// the body doesn't match, or if there is neither a `Digest` header with a supported
+    /// algorithm nor a `Content-MD5` header.
+    ///
+    /// ```no_run
+    /// let mut res = ureq::get("http://example.com/download")
+    ///     .call()?;
+    ///
+    /// let bytes = res.body_mut().read_to_vec_verified()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "digest")]
+    pub fn read_to_vec_verified(&mut self) -> Result<Vec<u8>, Error> {
+        // Read the body as sent, to verify it before decoding.
+        let empty = BodySource::Buffered(io::Cursor::new(Arc::from(&[][..])));
+        let source = std::mem::replace(&mut self.source, empty);
+
+        let capacity = self.known_size().unwrap_or(0).min(MAX_BODY_SIZE) as usize;
+        let mut raw = Vec::with_capacity(capacity);
+        LimitReader::new(BodySourceRef::Owned(source), MAX_BODY_SIZE).read_to_end(&mut raw)?;
+
+        digest::verify(
+            self.info.digest.as_deref(),
+            self.info.content_md5.as_deref(),
+            &raw,
+        )?;
+
+        self.source = BodySource::Buffered(io::Cursor::new(raw.into()));
+        self.read_to_vec()
+    }
+
+    /// Save the response body to a file.
+    ///
+    /// * Body is not limited.
+    ///
+    /// The body is first written to a temporary file next to `path`, which is renamed
+    /// into place once the whole body is read. An interrupted download thus never leaves
+    /// a partial file at `path`. On error the temporary file is removed.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// ```no_run
+    /// let mut res = ureq::get("http://example.com/file.tar.gz")
+    ///     .call()?;
+    ///
+    /// let written = res.body_mut().save_to_file("file.tar.gz")?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn save_to_file(&mut self, path: impl AsRef<Path>) -> Result<u64, Error> {
+        let path = path.as_ref();
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(".{}.tmp", crate::util::random_key()));
+        let temp_path = path.with_file_name(temp_name);
+
+        let result = (|| {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)?;
+
+            let written = io::copy(&mut self.as_reader(), &mut file)?;
+            file.sync_all()?;
+            fs::rename(&temp_path, path)?;
+
+            Ok(written)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+
+        result
+    }
+
+    /// Copy the response body to a writer, such as a file or socket.
+    ///
+    /// * Body is not limited.
+    ///
+    /// Returns the number of bytes written, which is 0 for responses without body, such as
+    /// for HEAD requests, `204` and `304`. Errors reading the body, such as timeouts, are
+    /// returned as is.
+    ///
+    /// ```
+    /// let mut res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// let mut out = std::io::sink();
+    /// let written = res.body_mut().copy_to(&mut out)?;
+    /// assert_eq!(written, 100);
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn copy_to(&mut self, w: &mut impl io::Write) -> Result<u64, Error> {
+        let written = io::copy(&mut self.as_reader(), w)?;
+        Ok(written)
+    }
+
     /// Read the response from JSON.
     ///
     /// * Response is limited to 10MB.
     ///
-    /// To change this default use [`Body::as_reader()`] and deserialize JSON manually.
+    /// To change this default use [`Body::with_config()`], for example
+    /// `body.with_config().limit(1024 * 1024).read_json()`. A body over the limit
+    /// errors with [`Error::BodyExceedsLimit`].
     ///
     /// The returned value is something that derives [`Deserialize`](serde::Deserialize).
     /// You might need to be explicit with which type you want. See example below.
@@ -243,9 +560,33 @@ impl Body {
     /// ```
     #[cfg(feature = "json")]
     pub fn read_json<T: serde::de::DeserializeOwned>(&mut self) -> Result<T, Error> {
-        let reader = self.with_config().limit(MAX_BODY_SIZE).into_reader();
-        let value: T = serde_json::from_reader(reader)?;
-        Ok(value)
+        self.with_config().limit(MAX_BODY_SIZE).read_json()
+    }
+
+    /// Read the response from JSON, and keep the raw bytes.
+    ///
+    /// * Response is limited to 10MB.
+    ///
+    /// The body is read once. The bytes are returned as received (after any
+    /// decompression), which is useful to store a response verbatim for auditing.
+    ///
+    /// ```
+    /// let (value, raw) = ureq::get("https://httpbin.org/json")
+    ///     .call()?
+    ///     .body_mut()
+    ///     .read_json_with_bytes::<serde_json::Value>()?;
+    ///
+    /// assert_eq!(value["slideshow"]["author"], "Yours Truly");
+    /// assert!(!raw.is_empty());
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn read_json_with_bytes<T: serde::de::DeserializeOwned>(
+        &mut self,
+    ) -> Result<(T, Vec<u8>), Error> {
+        let raw = self.read_to_vec()?;
+        let value: T = serde_json::from_slice(&raw)?;
+        Ok((value, raw))
     }
 
     /// Read the body data with configuration.
@@ -265,8 +606,8 @@ impl Body {
     ///     .into_reader();
     /// # Ok::<_, ureq::Error>(())
     /// ```
-    pub fn with_config(&mut self) -> BodyWithConfig {
-        let handler = UnitHandlerRef::Shared(&mut self.unit_handler);
+    pub fn with_config(&mut self) -> BodyWithConfig<'_> {
+        let handler = BodySourceRef::Shared(&mut self.source);
         BodyWithConfig::new(handler, self.info.clone())
     }
 
@@ -290,9 +631,160 @@ impl Body {
     /// # Ok::<_, ureq::Error>(())
     /// ```
     pub fn into_with_config(self) -> BodyWithConfig<'static> {
-        let handler = UnitHandlerRef::Owned(self.unit_handler);
+        let handler = BodySourceRef::Owned(self.source);
         BodyWithConfig::new(handler, self.info.clone())
     }
+
+    /// Builder for a body with data in memory.
+    ///
+    /// Used together with [`http::Response::builder()`] to create responses, such as
+    /// for unit tests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::Body;
+    /// use ureq::http::Response;
+    ///
+    /// let body = Body::builder()
+    ///     .mime_type("text/plain")
+    ///     .charset("utf-8")
+    ///     .data("hello");
+    ///
+    /// let mut res = Response::builder()
+    ///     .status(200)
+    ///     .header("x-foo", "bar")
+    ///     .body(body)?;
+    ///
+    /// assert_eq!(res.body_mut().read_to_string()?, "hello");
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn builder() -> BodyBuilder {
+        BodyBuilder {
+            mime_type: None,
+            charset: None,
+            content_encoding: ContentEncoding::None,
+        }
+    }
+
+    /// The size of the body before decoding, if known up front.
+    pub(crate) fn known_size(&self) -> Option<u64> {
+        match self.info.body_mode {
+            BodyMode::NoBody => Some(0),
+            BodyMode::LengthDelimited(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Continue this partial body, the start of a resource of size `total`.
+    ///
+    /// Whenever the body so far is read to end, `next` is called with the offset of the
+    /// missing data, to get a body continuing from there.
+    pub(crate) fn into_ranges(
+        self,
+        total: u64,
+        next: impl FnMut(u64) -> Result<Body, Error> + Send + Sync + 'static,
+    ) -> Body {
+        let mut info = (*self.info).clone();
+        info.body_mode = BodyMode::LengthDelimited(total);
+
+        Body {
+            info: Arc::new(info),
+            source: BodySource::Ranges(Box::new(RangeSource::new(
+                self.source,
+                total,
+                Box::new(next),
+            ))),
+        }
+    }
+
+    /// Read the entire body, without decoding, into memory.
+    pub(crate) fn into_buffered(self) -> Result<BufferedBody, Error> {
+        let capacity = self.known_size().unwrap_or(0).min(MAX_BODY_SIZE) as usize;
+        let mut data = Vec::with_capacity(capacity);
+        BodySourceRef::Owned(self.source).read_to_end(&mut data)?;
+
+        Ok(BufferedBody {
+            info: self.info,
+            data: data.into(),
+        })
+    }
+}
+
+/// A body read to memory, that can be turned into any number of new bodies.
+///
+/// The data is kept as received, and decoded (gzip, charset etc) when reading
+/// the new bodies.
+#[derive(Clone)]
+pub(crate) struct BufferedBody {
+    info: Arc<ResponseInfo>,
+    data: Arc<[u8]>,
+}
+
+impl BufferedBody {
+    pub fn to_body(&self) -> Body {
+        Body {
+            info: self.info.clone(),
+            source: BodySource::Buffered(io::Cursor::new(self.data.clone())),
+        }
+    }
+}
+
+/// Builder of a [`Body`] with data in memory.
+///
+/// Obtained via [`Body::builder()`].
+#[derive(Debug)]
+pub struct BodyBuilder {
+    mime_type: Option<String>,
+    charset: Option<String>,
+    content_encoding: ContentEncoding,
+}
+
+impl BodyBuilder {
+    /// The mime type of the body, such as `text/plain`.
+    pub fn mime_type(mut self, value: impl Into<String>) -> Self {
+        self.mime_type = Some(value.into());
+        self
+    }
+
+    /// The charset of the body, such as `utf-8`.
+    ///
+    /// Text is decoded from this charset when read, if the **charset** feature is enabled.
+    pub fn charset(mut self, value: impl Into<String>) -> Self {
+        self.charset = Some(value.into());
+        self
+    }
+
+    /// The content encoding of the data, such as `gzip`.
+    ///
+    /// The data is decompressed when read, if the corresponding feature is enabled.
+    pub fn content_encoding(mut self, value: &str) -> Self {
+        self.content_encoding = ContentEncoding::from(value);
+        self
+    }
+
+    /// Create the body from the data.
+    pub fn data(self, data: impl Into<Vec<u8>>) -> Body {
+        let data: Vec<u8> = data.into();
+
+        let info = ResponseInfo {
+            content_encoding: self.content_encoding,
+            mime_type: self.mime_type,
+            charset: self.charset,
+            body_mode: BodyMode::LengthDelimited(data.len() as u64),
+            max_decompressed: None,
+            #[cfg(feature = "digest")]
+            digest: None,
+            #[cfg(feature = "digest")]
+            content_md5: None,
+        };
+
+        BufferedBody {
+            info: Arc::new(info),
+            data: data.into(),
+        }
+        .to_body()
+    }
 }
 
 /// Configuration of how to read the body.
@@ -303,19 +795,21 @@ impl Body {
 /// * [Body::into_with_config()]
 ///
 pub struct BodyWithConfig<'a> {
-    handler: UnitHandlerRef<'a>,
+    handler: BodySourceRef<'a>,
     info: Arc<ResponseInfo>,
     limit: u64,
     lossy_utf8: bool,
+    charset: Option<String>,
 }
 
 impl<'a> BodyWithConfig<'a> {
-    fn new(handler: UnitHandlerRef<'a>, info: Arc<ResponseInfo>) -> Self {
+    fn new(handler: BodySourceRef<'a>, info: Arc<ResponseInfo>) -> Self {
         BodyWithConfig {
             handler,
             info,
             limit: u64::MAX,
             lossy_utf8: false,
+            charset: None,
         }
     }
 
@@ -342,12 +836,38 @@ impl<'a> BodyWithConfig<'a> {
         self
     }
 
+    /// Decode the body from this charset, rather than the one in the content-type.
+    ///
+    /// Requires the **charset** feature.
+    ///
+    /// For servers that send the wrong charset, or none. The body is treated as text
+    /// whatever the content-type, so [`BodyWithConfig::lossy_utf8()`] applies. With
+    /// `lossy_utf8(false)`, invalid utf-8 is an error. Other charsets are always decoded
+    /// leniently, with malformed sequences replaced. An unknown label means utf-8.
+    ///
+    /// ```
+    /// let mut res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// let text = res.body_mut()
+    ///     .with_config()
+    ///     .charset("iso-8859-1")
+    ///     .read_to_string()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "charset")]
+    pub fn charset(mut self, label: impl Into<String>) -> Self {
+        self.charset = Some(label.into());
+        self
+    }
+
     fn do_build(self) -> BodyReader<'a> {
         BodyReader::new(
             LimitReader::new(self.handler, self.limit),
             &self.info,
             self.info.body_mode,
             self.lossy_utf8,
+            self.charset.as_deref(),
         )
     }
 
@@ -357,26 +877,42 @@ impl<'a> BodyWithConfig<'a> {
     }
 
     /// Read into string.
+    ///
+    /// The body is decoded incrementally straight into the string.
     pub fn read_to_string(self) -> Result<String, Error> {
+        let limit = self.limit;
         let mut reader = self.do_build();
-        let mut buf = String::new();
+        let mut buf = String::with_capacity(reader.size_hint(limit));
         reader.read_to_string(&mut buf)?;
         Ok(buf)
     }
 
     /// Read into vector.
     pub fn read_to_vec(self) -> Result<Vec<u8>, Error> {
+        let limit = self.limit;
         let mut reader = self.do_build();
-        let mut buf = Vec::new();
+        let mut buf = Vec::with_capacity(reader.size_hint(limit));
         reader.read_to_end(&mut buf)?;
         Ok(buf)
     }
 
+    /// Read into a seekable in-memory cursor.
+    pub fn read_to_seekable(self) -> Result<io::Cursor<Vec<u8>>, Error> {
+        self.read_to_vec().map(io::Cursor::new)
+    }
+
     /// Read JSON body.
     #[cfg(feature = "json")]
     pub fn read_json<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
         let reader = self.do_build();
-        let value: T = serde_json::from_reader(reader)?;
+        let value: T = serde_json::from_reader(reader).map_err(|e| {
+            // Errors reading the body, such as timeouts, are not JSON errors.
+            if e.is_io() {
+                Error::from(io::Error::from(e))
+            } else {
+                Error::Json(e)
+            }
+        })?;
         Ok(value)
     }
 }
@@ -385,12 +921,17 @@ impl<'a> BodyWithConfig<'a> {
 enum ContentEncoding {
     None,
     Gzip,
+    Deflate,
     Brotli,
     Unknown,
 }
 
 impl ResponseInfo {
-    pub fn new(headers: &http::HeaderMap, body_mode: BodyMode) -> Self {
+    pub fn new(
+        headers: &http::HeaderMap,
+        body_mode: BodyMode,
+        max_decompressed: Option<u64>,
+    ) -> Self {
         let content_encoding = headers
             .get("content-encoding")
             .and_then(|v| v.to_str().ok())
@@ -408,6 +949,17 @@ impl ResponseInfo {
             mime_type,
             charset,
             body_mode,
+            max_decompressed,
+            #[cfg(feature = "digest")]
+            digest: headers
+                .get("digest")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            #[cfg(feature = "digest")]
+            content_md5: headers
+                .get("content-md5")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
         }
     }
 
@@ -422,20 +974,12 @@ impl ResponseInfo {
 
 fn split_content_type(content_type: &str) -> (Option<String>, Option<String>) {
     // Content-Type: text/plain; charset=iso-8859-1
-    let mut split = content_type.split(';');
-
-    let Some(mime_type) = split.next() else {
-        return (None, None);
-    };
+    let (mime_type, params) = parse_content_type(content_type);
 
-    let mut charset = None;
-
-    for maybe_charset in split {
-        let maybe_charset = maybe_charset.trim();
-        if let Some(s) = maybe_charset.strip_prefix("charset=") {
-            charset = Some(s.to_string());
-        }
-    }
+    let charset = params
+        .into_iter()
+        .find(|(name, _)| name == "charset")
+        .map(|(_, value)| value);
 
     (Some(mime_type.to_string()), charset)
 }
@@ -465,12 +1009,12 @@ fn split_content_type(content_type: &str) -> (Option<String>, Option<String>) {
 ///
 /// ```
 /// use std::io::Read;
+/// use ureq::ResponseExt;
+///
 /// let mut res = ureq::get("http://httpbin.org/bytes/100")
 ///     .call()?;
 ///
-/// assert!(res.headers().contains_key("Content-Length"));
-/// let len: usize = res.headers().get("Content-Length")
-///     .unwrap().to_str().unwrap().parse().unwrap();
+/// let len = res.content_length().unwrap() as usize;
 ///
 /// let mut bytes: Vec<u8> = Vec::with_capacity(len);
 /// res.body_mut().as_reader()
@@ -480,20 +1024,37 @@ fn split_content_type(content_type: &str) -> (Option<String>, Option<String>) {
 /// # Ok::<_, ureq::Error>(())
 /// ```
 pub struct BodyReader<'a> {
-    reader: MaybeLossyDecoder<CharsetDecoder<ContentDecoder<LimitReader<UnitHandlerRef<'a>>>>>,
+    reader: MaybeLossyDecoder<
+        CharsetDecoder<DecompressLimit<ContentDecoder<LimitReader<BodySourceRef<'a>>>>>,
+    >,
     // If this reader is used as SendBody for another request, this
     // body mode can indiciate the content-length. Gzip, charset etc
     // would mean input is not same as output.
     outgoing_body_mode: BodyMode,
+    bytes_read: u64,
 }
 
 impl<'a> BodyReader<'a> {
     fn new(
-        reader: LimitReader<UnitHandlerRef<'a>>,
+        reader: LimitReader<BodySourceRef<'a>>,
         info: &ResponseInfo,
         incoming_body_mode: BodyMode,
         lossy_utf8: bool,
+        charset: Option<&str>,
     ) -> BodyReader<'a> {
+        // A response without body (HEAD, 204, 304) has nothing to decode, even if
+        // the headers say otherwise. The connection is already back in the pool.
+        if incoming_body_mode == BodyMode::NoBody {
+            let reader = ContentDecoder::PassThrough(reader);
+            let reader = DecompressLimit::new(reader, None);
+            let reader = CharsetDecoder::PassThrough(reader);
+            return BodyReader {
+                outgoing_body_mode: BodyMode::NoBody,
+                reader: MaybeLossyDecoder::PassThrough(reader),
+                bytes_read: 0,
+            };
+        }
+
         // This is outgoing body_mode in case we are using the BodyReader as a send body
         // in a proxy situation.
         let mut outgoing_body_mode = incoming_body_mode;
@@ -508,6 +1069,14 @@ impl<'a> BodyReader<'a> {
             }
             #[cfg(not(feature = "gzip"))]
             ContentEncoding::Gzip => ContentDecoder::PassThrough(reader),
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Deflate => {
+                debug!("Decoding deflate");
+                outgoing_body_mode = BodyMode::Chunked;
+                ContentDecoder::Deflate(Box::new(deflate::DeflateDecoder::new(reader)))
+            }
+            #[cfg(not(feature = "gzip"))]
+            ContentEncoding::Deflate => ContentDecoder::PassThrough(reader),
             #[cfg(feature = "brotli")]
             ContentEncoding::Brotli => {
                 debug!("Decoding brotli");
@@ -518,18 +1087,29 @@ impl<'a> BodyReader<'a> {
             ContentEncoding::Brotli => ContentDecoder::PassThrough(reader),
         };
 
-        let reader = if info.is_text() {
+        let max_decompressed = if matches!(reader, ContentDecoder::PassThrough(_)) {
+            None
+        } else {
+            info.max_decompressed
+        };
+        let reader = DecompressLimit::new(reader, max_decompressed);
+
+        // A charset set by the user overrides the content-type, and makes the body text.
+        let is_text = charset.is_some() || info.is_text();
+        let charset = charset.or(info.charset.as_deref());
+
+        let reader = if is_text {
             charset_decoder(
                 reader,
                 info.mime_type.as_deref(),
-                info.charset.as_deref(),
+                charset,
                 &mut outgoing_body_mode,
             )
         } else {
             CharsetDecoder::PassThrough(reader)
         };
 
-        let reader = if info.is_text() && lossy_utf8 {
+        let reader = if is_text && lossy_utf8 {
             MaybeLossyDecoder::Lossy(LossyUtf8Reader::new(reader))
         } else {
             MaybeLossyDecoder::PassThrough(reader)
@@ -538,11 +1118,47 @@ impl<'a> BodyReader<'a> {
         BodyReader {
             outgoing_body_mode,
             reader,
+            bytes_read: 0,
         }
     }
 
-    pub(crate) fn body_mode(&self) -> BodyMode {
-        self.outgoing_body_mode
+    /// The number of body bytes read so far.
+    ///
+    /// This counts the bytes returned by the reader, i.e. after decompression and charset
+    /// conversion. Without those, this can be compared to the `Content-Length` header
+    /// to report download progress.
+    ///
+    /// ```
+    /// use std::io::Read;
+    ///
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// let mut reader = res.into_body().into_reader();
+    /// let mut buf = [0; 10];
+    /// reader.read_exact(&mut buf)?;
+    ///
+    /// assert_eq!(reader.bytes_read(), 10);
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    pub(crate) fn body_mode(&self) -> BodyMode {
+        self.outgoing_body_mode
+    }
+
+    /// Capacity to allocate up front when reading the entire body.
+    ///
+    /// Reading to the exact size avoids repeatedly growing the buffer, which can
+    /// double the peak memory. The server provided length is not trusted beyond
+    /// the default max body size.
+    fn size_hint(&self, limit: u64) -> usize {
+        match self.outgoing_body_mode {
+            BodyMode::LengthDelimited(v) => v.min(limit).min(MAX_BODY_SIZE) as usize,
+            _ => 0,
+        }
     }
 }
 
@@ -593,7 +1209,9 @@ impl<R: io::Read> io::Read for MaybeLossyDecoder<R> {
 
 impl<'a> Read for BodyReader<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.reader.read(buf)
+        let n = self.reader.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
     }
 }
 
@@ -616,6 +1234,8 @@ impl<R: io::Read> Read for CharsetDecoder<R> {
 enum ContentDecoder<R: io::Read> {
     #[cfg(feature = "gzip")]
     Gzip(Box<gzip::GzipDecoder<R>>),
+    #[cfg(feature = "gzip")]
+    Deflate(Box<deflate::DeflateDecoder<R>>),
     #[cfg(feature = "brotli")]
     Brotli(Box<brotli::BrotliDecoder<R>>),
     PassThrough(R),
@@ -626,6 +1246,8 @@ impl<R: Read> Read for ContentDecoder<R> {
         match self {
             #[cfg(feature = "gzip")]
             ContentDecoder::Gzip(v) => v.read(buf),
+            #[cfg(feature = "gzip")]
+            ContentDecoder::Deflate(v) => v.read(buf),
             #[cfg(feature = "brotli")]
             ContentDecoder::Brotli(v) => v.read(buf),
             ContentDecoder::PassThrough(v) => v.read(buf),
@@ -643,6 +1265,7 @@ impl From<&str> for ContentEncoding {
     fn from(s: &str) -> Self {
         match s {
             "gzip" => ContentEncoding::Gzip,
+            "deflate" => ContentEncoding::Deflate,
             "br" => ContentEncoding::Brotli,
             _ => {
                 info!("Unknown content-encoding: {}", s);
@@ -654,11 +1277,12 @@ impl From<&str> for ContentEncoding {
 
 #[cfg(all(test, feature = "_test"))]
 mod test {
-    use std::iter;
+    use std::io::Read;
+    use std::time::{Duration, Instant};
 
     use crate::test::init_test_log;
-    use crate::transport::set_handler;
-    use crate::Error;
+    use crate::transport::{set_handler, set_handler_delivery, set_handler_version, Delivery};
+    use crate::{Agent, AgentConfig, Error, TimeoutReason, Timeouts};
 
     #[test]
     fn content_type_without_charset() {
@@ -685,6 +1309,159 @@ mod test {
         assert_eq!(res.body().charset(), Some("iso-8859-4"));
     }
 
+    #[test]
+    #[cfg(feature = "charset")]
+    fn body_builder_decodes_charset() {
+        let body = crate::Body::builder()
+            .mime_type("text/plain")
+            .charset("iso-8859-1")
+            .data(&b"h\xe9llo"[..]);
+
+        let mut res = http::Response::builder().status(200).body(body).unwrap();
+        assert_eq!(res.body().mime_type(), Some("text/plain"));
+        assert_eq!(res.body().charset(), Some("iso-8859-1"));
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "h\u{e9}llo");
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn body_builder_decodes_content_encoding() {
+        use std::io::Write;
+
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(b"hello").unwrap();
+        let data = enc.finish().unwrap();
+
+        let mut body = crate::Body::builder().content_encoding("gzip").data(data);
+        assert_eq!(body.read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    #[cfg(feature = "charset")]
+    fn forced_charset_ignores_header() {
+        let mut body = crate::Body::builder()
+            .mime_type("text/plain")
+            .charset("utf-8")
+            .data(&b"caf\xe9"[..]);
+        let text = body
+            .with_config()
+            .charset("windows-1252")
+            .read_to_string()
+            .unwrap();
+        assert_eq!(text, "caf\u{e9}");
+
+        // An unknown label is utf-8, also when the header says otherwise.
+        let mut body = crate::Body::builder()
+            .charset("iso-8859-1")
+            .data("caf\u{e9}");
+        let text = body
+            .with_config()
+            .charset("not-a-charset")
+            .read_to_string()
+            .unwrap();
+        assert_eq!(text, "caf\u{e9}");
+    }
+
+    #[test]
+    fn truncated_bodies_same_error() {
+        init_test_log();
+        set_handler(
+            "/truncated_length",
+            200,
+            &[("content-length", "10")],
+            b"hello",
+        );
+        set_handler(
+            "/truncated_chunked",
+            200,
+            &[("transfer-encoding", "chunked")],
+            b"5\r\nhello\r\n5\r\nwo",
+        );
+
+        for path in ["/truncated_length", "/truncated_chunked"] {
+            let mut res = crate::get(format!("https://my.test{}", path))
+                .call()
+                .unwrap();
+            let err = res.body_mut().read_to_string().unwrap_err();
+            assert!(err.is_connection_error());
+            let ioe = err.into_io();
+            assert_eq!(ioe.kind(), std::io::ErrorKind::UnexpectedEof);
+            assert_eq!(
+                ioe.to_string(),
+                "response body closed before all bytes were read"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_charset_falls_back_to_utf8() {
+        init_test_log();
+        set_handler(
+            "/bogus_charset",
+            200,
+            &[
+                ("content-type", "text/plain; charset=not-a-real-charset"),
+                ("content-length", "7"),
+            ],
+            b"h\xc3\xa9ll\xffo",
+        );
+
+        // The label is kept as given.
+        let mut res = crate::get("https://my.test/bogus_charset").call().unwrap();
+        assert_eq!(res.body().charset(), Some("not-a-real-charset"));
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "h\u{e9}ll?o");
+
+        // Without lossy utf-8, the invalid byte is an error rather than a panic.
+        let mut res = crate::get("https://my.test/bogus_charset").call().unwrap();
+        let err = res.body_mut().with_config().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    #[cfg(feature = "charset")]
+    fn forced_charset() {
+        init_test_log();
+        set_handler(
+            "/forced_charset",
+            200,
+            &[
+                ("content-type", "application/octet-stream"),
+                ("content-length", "5"),
+            ],
+            b"caf\xe9!",
+        );
+
+        let mut res = crate::get("https://my.test/forced_charset").call().unwrap();
+        let text = res
+            .body_mut()
+            .with_config()
+            .charset("iso-8859-1")
+            .read_to_string()
+            .unwrap();
+        assert_eq!(text, "caf\u{e9}!");
+
+        // Forced utf-8, strict, on invalid bytes.
+        let mut res = crate::get("https://my.test/forced_charset").call().unwrap();
+        let err = res
+            .body_mut()
+            .with_config()
+            .charset("utf-8")
+            .read_to_string()
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+
+        // Forced utf-8, lenient.
+        let mut res = crate::get("https://my.test/forced_charset").call().unwrap();
+        let text = res
+            .body_mut()
+            .with_config()
+            .charset("utf-8")
+            .lossy_utf8(true)
+            .read_to_string()
+            .unwrap();
+        assert_eq!(text, "caf?!");
+    }
+
     #[test]
     fn chunked_transfer() {
         init_test_log();
@@ -714,14 +1491,589 @@ mod test {
         set_handler(
             "/get",
             200,
-            &[(
-                "content-type",
-                &iter::repeat('b').take(64 * 1024).collect::<String>(),
-            )],
+            &[("content-type", &"b".repeat(64 * 1024))],
             b"{}",
         );
 
         let err = crate::get("https://my.test/get").call().unwrap_err();
         assert!(matches!(err, Error::LargeResponseHeader(_, _)));
     }
+
+    #[test]
+    fn too_many_response_headers() {
+        init_test_log();
+        let names: Vec<String> = (0..200).map(|i| format!("x-header-{}", i)).collect();
+        let headers: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), "1")).collect();
+        set_handler("/many_headers", 200, &headers, b"");
+
+        let err = crate::get("https://my.test/many_headers")
+            .call()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Protocol(hoot::Error::HttpParseTooManyHeaders)
+        ));
+    }
+
+    #[test]
+    fn conflicting_content_length() {
+        init_test_log();
+        set_handler(
+            "/conflicting_length",
+            200,
+            &[("content-length", "5"), ("content-length", "11")],
+            b"hello world",
+        );
+
+        let err = crate::get("https://my.test/conflicting_length")
+            .call()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Protocol(hoot::Error::TooManyContentLengthHeaders)
+        ));
+    }
+
+    #[test]
+    fn unexpected_switching_protocols() {
+        init_test_log();
+        set_handler(
+            "/switch",
+            101,
+            &[("upgrade", "websocket"), ("connection", "upgrade")],
+            b"\x81\x05hello",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let err = agent.get("https://my.test/switch").call().unwrap_err();
+        assert!(matches!(err, Error::UnsupportedUpgrade));
+        assert_eq!(agent.pool_count(), 0);
+    }
+
+    #[test]
+    fn repeated_equal_content_length() {
+        init_test_log();
+        set_handler(
+            "/repeated_length",
+            200,
+            &[("content-length", "5"), ("content-length", "5")],
+            b"hello",
+        );
+
+        let mut res = crate::get("https://my.test/repeated_length")
+            .call()
+            .unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn head_with_erroneous_body() {
+        init_test_log();
+        set_handler(
+            "/head_erroneous",
+            200,
+            &[("content-length", "10")],
+            b"0123456789",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let mut res = agent.head("https://my.test/head_erroneous").call().unwrap();
+
+        // The stray body bytes are discarded and the connection returned to the pool.
+        assert_eq!(agent.pool_count(), 1);
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "");
+    }
+
+    #[test]
+    fn head_with_large_erroneous_body() {
+        init_test_log();
+        let body = "b".repeat(20 * 1024);
+        set_handler(
+            "/head_erroneous_large",
+            200,
+            &[("content-length", "20480")],
+            body.as_bytes(),
+        );
+
+        let agent = Agent::new_with_defaults();
+        let mut res = agent
+            .head("https://my.test/head_erroneous_large")
+            .call()
+            .unwrap();
+
+        // Too much unexpected data, the connection is closed.
+        assert_eq!(agent.pool_count(), 0);
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "");
+    }
+
+    #[test]
+    fn no_content_is_empty_and_pooled() {
+        init_test_log();
+        set_handler(
+            "/no_content",
+            204,
+            &[
+                ("content-encoding", "gzip"),
+                ("content-type", "text/plain; charset=iso-8859-1"),
+            ],
+            &[],
+        );
+
+        let agent = Agent::new_with_defaults();
+        let res = agent.get("https://my.test/no_content").call().unwrap();
+        assert_eq!(agent.pool_count(), 1);
+
+        let mut bytes = Vec::new();
+        res.into_body()
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn http10_keep_alive_is_pooled() {
+        init_test_log();
+        set_handler_version(
+            "/http10_keep_alive",
+            "HTTP/1.0",
+            200,
+            &[("connection", "keep-alive"), ("content-length", "5")],
+            b"hello",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let mut res = agent
+            .get("https://my.test/http10_keep_alive")
+            .call()
+            .unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+        assert_eq!(agent.pool_count(), 1);
+    }
+
+    #[test]
+    fn http10_without_keep_alive_is_closed() {
+        init_test_log();
+        set_handler_version(
+            "/http10_close",
+            "HTTP/1.0",
+            200,
+            &[("content-length", "5")],
+            b"hello",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let mut res = agent.get("https://my.test/http10_close").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+        assert_eq!(agent.pool_count(), 0);
+    }
+
+    #[test]
+    fn large_body_after_small_header() {
+        init_test_log();
+        // Header and body arrive in one read, which is bigger than the header limit.
+        let body = vec![b'x'; 100 * 1024];
+        set_handler(
+            "/large_body",
+            200,
+            &[("content-length", &body.len().to_string())],
+            &body,
+        );
+
+        let mut res = crate::get("https://my.test/large_body").call().unwrap();
+        let read = res
+            .body_mut()
+            .with_config()
+            .limit(1024 * 1024)
+            .read_to_vec()
+            .unwrap();
+        assert_eq!(read, body);
+    }
+
+    #[test]
+    fn read_large_utf8_body() {
+        init_test_log();
+        // Multi-byte chars are split across read boundaries.
+        let body = "åäö€".repeat(300_000);
+        let len = body.len().to_string();
+        set_handler(
+            "/large_utf8",
+            200,
+            &[("content-type", "text/plain"), ("content-length", &len)],
+            body.as_bytes(),
+        );
+
+        let mut res = crate::get("https://my.test/large_utf8").call().unwrap();
+        let s = res
+            .body_mut()
+            .with_config()
+            .limit(10 * 1024 * 1024)
+            .read_to_string()
+            .unwrap();
+
+        assert_eq!(s, body);
+        // Allocated once for the content-length, never grown.
+        assert_eq!(s.capacity(), body.len());
+    }
+
+    #[test]
+    fn body_reader_counts_bytes_read() {
+        init_test_log();
+        set_handler(
+            "/bytes_read",
+            200,
+            &[("content-length", "10")],
+            b"0123456789",
+        );
+
+        let res = crate::get("https://my.test/bytes_read").call().unwrap();
+        let mut reader = res.into_body().into_reader();
+        assert_eq!(reader.bytes_read(), 0);
+
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.bytes_read(), 4);
+
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(reader.bytes_read(), 10);
+    }
+
+    #[test]
+    fn read_to_seekable_seeks() {
+        use std::io::{Seek, SeekFrom};
+
+        init_test_log();
+        set_handler("/seekable", 200, &[("content-length", "10")], b"0123456789");
+
+        let mut res = crate::get("https://my.test/seekable").call().unwrap();
+        let mut cursor = res.body_mut().read_to_seekable().unwrap();
+
+        cursor.seek(SeekFrom::End(-3)).unwrap();
+        let mut end = String::new();
+        cursor.read_to_string(&mut end).unwrap();
+        assert_eq!(end, "789");
+
+        let mut res = crate::get("https://my.test/seekable").call().unwrap();
+        let err = res
+            .body_mut()
+            .with_config()
+            .limit(5)
+            .read_to_seekable()
+            .unwrap_err();
+        assert!(matches!(err, Error::BodyExceedsLimit(5)));
+    }
+
+    #[test]
+    fn save_to_file_is_atomic() {
+        init_test_log();
+        let dir = std::env::temp_dir().join(format!("ureq-{}", crate::util::random_key()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("saved.txt");
+
+        set_handler("/save_ok", 200, &[("content-length", "5")], b"hello");
+        let mut res = crate::get("https://my.test/save_ok").call().unwrap();
+        assert_eq!(res.body_mut().save_to_file(&path).unwrap(), 5);
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+        // Server disconnects before the full body.
+        set_handler("/save_short", 200, &[("content-length", "100")], b"partial");
+        let mut res = crate::get("https://my.test/save_short").call().unwrap();
+        res.body_mut().save_to_file(&path).unwrap_err();
+
+        // The earlier file is untouched, and no temporary file is left.
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn read_json_errors() {
+        init_test_log();
+        set_handler(
+            "/bad_json",
+            200,
+            &[
+                ("content-type", "application/json"),
+                ("content-length", "22"),
+            ],
+            b"{\n  \"a\": 1,\n  \"b\": ]\n}",
+        );
+        set_handler_delivery(
+            "/stall_json",
+            200,
+            &[("content-length", "10")],
+            b"{\"a\": 1}  ",
+            Delivery::StallAfterHeaders,
+        );
+
+        let mut res = crate::get("https://my.test/bad_json").call().unwrap();
+        let err = res.body_mut().read_json::<serde_json::Value>().unwrap_err();
+        let Error::Json(e) = err else {
+            panic!("expected json error: {:?}", err);
+        };
+        assert_eq!((e.line(), e.column()), (3, 8));
+
+        let agent: Agent = AgentConfig {
+            timeouts: Timeouts {
+                recv_body: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/stall_json").call().unwrap();
+        let err = res.body_mut().read_json::<serde_json::Value>().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::RecvBody)));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn read_json_with_limit() {
+        init_test_log();
+        set_handler(
+            "/json_limit",
+            200,
+            &[
+                ("content-type", "application/json"),
+                ("content-length", "12"),
+            ],
+            b"{\"a\": \"bcd\"}",
+        );
+
+        let mut res = crate::get("https://my.test/json_limit").call().unwrap();
+        let err = res
+            .body_mut()
+            .with_config()
+            .limit(10)
+            .read_json::<serde_json::Value>()
+            .unwrap_err();
+        assert!(matches!(err, Error::BodyExceedsLimit(10)));
+
+        let mut res = crate::get("https://my.test/json_limit").call().unwrap();
+        let value = res
+            .body_mut()
+            .with_config()
+            .limit(12)
+            .read_json::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(value["a"], "bcd");
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn read_json_with_bytes_keeps_raw() {
+        init_test_log();
+        let body = b"{ \"a\" : [1, 2] }";
+        set_handler(
+            "/json_raw",
+            200,
+            &[
+                ("content-type", "application/json"),
+                ("content-length", "16"),
+            ],
+            body,
+        );
+
+        let mut res = crate::get("https://my.test/json_raw").call().unwrap();
+        let (value, raw) = res
+            .body_mut()
+            .read_json_with_bytes::<serde_json::Value>()
+            .unwrap();
+
+        assert_eq!(value, serde_json::json!({ "a": [1, 2] }));
+        assert_eq!(raw, body);
+    }
+
+    #[test]
+    fn delivery_slow() {
+        init_test_log();
+        set_handler_delivery(
+            "/slow",
+            200,
+            &[("content-length", "5")],
+            b"hello",
+            Delivery::Slow(Duration::from_millis(20)),
+        );
+
+        let start = Instant::now();
+        let mut res = crate::get("https://my.test/slow").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn into_lines_one_at_a_time() {
+        init_test_log();
+        set_handler_delivery(
+            "/ndjson",
+            200,
+            &[("content-length", "12")],
+            b"{}\r\n{\"a\":1}\n",
+            Delivery::Slow(Duration::from_millis(20)),
+        );
+
+        let start = Instant::now();
+        let res = crate::get("https://my.test/ndjson").call().unwrap();
+        let mut lines = res.into_body().into_lines();
+
+        assert_eq!(lines.next().unwrap().unwrap(), "{}");
+        let first = start.elapsed();
+
+        assert_eq!(lines.next().unwrap().unwrap(), "{\"a\":1}");
+        assert!(lines.next().is_none());
+
+        // The first line is read before the rest of the body has arrived.
+        assert!(first < start.elapsed() - Duration::from_millis(100));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn into_json_lines_skips_blank() {
+        init_test_log();
+        set_handler(
+            "/ndjson_values",
+            200,
+            &[("content-length", "16")],
+            b"1\n\n  \r\n2\n{oops}\n",
+        );
+
+        let res = crate::get("https://my.test/ndjson_values").call().unwrap();
+        let mut values = res.into_body().into_json_lines::<u32>();
+
+        assert_eq!(values.next().unwrap().unwrap(), 1);
+        assert_eq!(values.next().unwrap().unwrap(), 2);
+        let err = values.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn copy_to_writer() {
+        init_test_log();
+        set_handler("/copy_to", 200, &[("content-length", "5")], b"hello");
+
+        let mut res = crate::get("https://my.test/copy_to").call().unwrap();
+        let mut out = vec![];
+        assert_eq!(res.body_mut().copy_to(&mut out).unwrap(), 5);
+        assert_eq!(out, b"hello");
+
+        let mut res = crate::head("https://my.test/copy_to").call().unwrap();
+        let mut out = vec![];
+        assert_eq!(res.body_mut().copy_to(&mut out).unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn delivery_stall_after_headers() {
+        init_test_log();
+        set_handler_delivery(
+            "/stall",
+            200,
+            &[("content-length", "5")],
+            b"hello",
+            Delivery::StallAfterHeaders,
+        );
+
+        let agent: Agent = AgentConfig {
+            timeouts: Timeouts {
+                recv_body: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/stall").call().unwrap();
+        let err = res.body_mut().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::RecvBody)));
+    }
+
+    #[test]
+    fn delivery_drop_after() {
+        init_test_log();
+        set_handler_delivery(
+            "/drop",
+            200,
+            &[("content-length", "5")],
+            b"hello",
+            Delivery::DropAfter(2),
+        );
+
+        let mut res = crate::get("https://my.test/drop").call().unwrap();
+        let err = res.body_mut().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn into_reader_timed_covers_remaining_ranges() {
+        init_test_log();
+
+        let read = |duration: Duration| {
+            let (_, body) = crate::get("https://my.test/partial")
+                .auto_complete_partial(true)
+                .call()
+                .unwrap()
+                .into_parts();
+            let mut bytes = Vec::new();
+            body.into_reader_timed(duration)
+                .read_to_end(&mut bytes)
+                .unwrap();
+            bytes
+        };
+
+        assert_eq!(read(Duration::from_secs(10)), b"hello world!");
+
+        // The time is up before the first read, so no ranges are requested.
+        assert_eq!(read(Duration::ZERO), b"");
+    }
+
+    #[test]
+    fn into_reader_timed_ends_cleanly() {
+        init_test_log();
+        set_handler_delivery(
+            "/timed_slow",
+            200,
+            &[("content-length", "10")],
+            b"0123456789",
+            Delivery::Slow(Duration::from_millis(50)),
+        );
+        set_handler_delivery(
+            "/timed_stall",
+            200,
+            &[("content-length", "10")],
+            b"0123456789",
+            Delivery::StallAfterHeaders,
+        );
+
+        let agent = Agent::new_with_defaults();
+
+        let (_, body) = agent
+            .get("https://my.test/timed_slow")
+            .call()
+            .unwrap()
+            .into_parts();
+        let mut bytes = Vec::new();
+        body.into_reader_timed(Duration::from_millis(120))
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert!(!bytes.is_empty() && bytes.len() < 10);
+
+        let (_, body) = agent
+            .get("https://my.test/timed_stall")
+            .call()
+            .unwrap()
+            .into_parts();
+        let mut bytes = Vec::new();
+        body.into_reader_timed(Duration::from_millis(50))
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert!(bytes.is_empty());
+
+        // Not read to end, so not pooled.
+        assert_eq!(agent.pool_count(), 0);
+    }
 }
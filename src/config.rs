@@ -1,7 +1,8 @@
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
-use hoot::client::flow::RedirectAuthHeaders;
+use http::uri::Scheme;
 use http::Uri;
 
 use crate::middleware::MiddlewareChain;
@@ -11,6 +12,9 @@ use crate::Proxy;
 #[cfg(feature = "_tls")]
 use crate::tls::TlsConfig;
 
+/// Callback for [`AgentConfig::on_header_line`].
+type HeaderLineFn = dyn Fn(&str) + Send + Sync;
+
 /// Config as built by AgentBuilder and then static for the lifetime of the Agent.
 ///
 /// When creating config instances, the `..Default::default()` pattern must be used.
@@ -63,6 +67,18 @@ pub struct AgentConfig {
     /// [`Agent::new_with_defaults()`][crate::Agent::new_with_defaults].
     pub proxy: Option<Proxy>,
 
+    /// Connect directly if connecting through the proxy fails.
+    ///
+    /// For networks where the proxy itself might be down. If the proxy host can't be resolved,
+    /// or the connection to it fails or times out, the request is made without the proxy.
+    /// Anything failing after the proxy is connected, such as the proxy refusing to connect
+    /// to the requested host or a TLS error, is not a reason to go direct.
+    ///
+    /// Only applies to HTTP(S) CONNECT proxies, not SOCKS.
+    ///
+    /// Defaults to `false`.
+    pub proxy_fallback_direct: bool,
+
     /// Disable Nagle's algorithm
     ///
     /// Set TCP_NODELAY. It's up to the transport whether this flag is honored.
@@ -77,10 +93,9 @@ pub struct AgentConfig {
 
     /// How to handle `Authorization` headers when following redirects
     ///
-    /// * `Never` (the default) means the authorization header is never attached to a redirected call.
-    /// * `SameHost` will keep the header when the redirect is to the same host and under https.
+    /// See [`RedirectAuthHeaders`] for the options.
     ///
-    /// Defaults to `None`.
+    /// Defaults to `SameOrigin`.
     pub redirect_auth_headers: RedirectAuthHeaders,
 
     /// Value to use for the `User-Agent` field
@@ -95,11 +110,24 @@ pub struct AgentConfig {
 
     /// Max size of the HTTP response header.
     ///
-    /// From the status, including all headers up until the body.
+    /// From the status, including all headers up until the body. This also bounds the
+    /// length of a single header line. Exceeding it gives
+    /// [`Error::LargeResponseHeader`](crate::Error::LargeResponseHeader). Apart from the
+    /// size, a response can have at most 128 headers.
     ///
     /// Defaults to `64KB`.
     pub max_response_header_size: usize,
 
+    /// Reject responses with both `Transfer-Encoding: chunked` and `Content-Length`.
+    ///
+    /// The body length of such a response is ambiguous. The HTTP spec says chunked wins,
+    /// which is what ureq does by default, dropping the `Content-Length` header and not
+    /// reusing the connection. Since the combination is a common sign of request
+    /// smuggling, security conscious clients might rather fail the request.
+    ///
+    /// Defaults to `false`.
+    pub reject_ambiguous_framing: bool,
+
     /// Default size of the input buffer
     ///
     /// The default connectors use this setting.
@@ -114,8 +142,20 @@ pub struct AgentConfig {
     /// Defaults to 512kb.
     pub output_buffer_size: usize,
 
+    /// Max size of a response body after decompression (gzip or brotli).
+    ///
+    /// Guards against decompression bombs, where a small compressed body expands
+    /// to a huge size. Reading past this size fails with
+    /// [`Error::DecompressExceedsLimit`](crate::Error::DecompressExceedsLimit).
+    /// Limits set when reading the body apply to the compressed size.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_decompressed_size: Option<u64>,
+
     /// Max number of idle pooled connections overall.
     ///
+    /// Set to 0 to disable connection pooling, making every request open a new connection.
+    ///
     /// Defaults to 10
     pub max_idle_connections: usize,
 
@@ -129,6 +169,52 @@ pub struct AgentConfig {
     /// Defaults to 15 seconds
     pub max_idle_age: Duration,
 
+    /// Max number of simultaneous in-flight requests per host/port combo.
+    ///
+    /// A request is in flight from opening the connection until the response body
+    /// is read to the end or dropped. Further requests to the same host block until
+    /// a slot frees up, or the connect timeout is reached.
+    ///
+    /// `Some(0)` would block every request forever, so it is treated as `Some(1)`.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_concurrent_per_host: Option<usize>,
+
+    /// Keep a copy of the request body, up to this size, in the response.
+    ///
+    /// The copy is available via [`ResponseExt::sent_body()`][crate::ResponseExt::sent_body].
+    /// Only bodies held in memory, such as `&str` or `Vec<u8>`, are retained. Bodies
+    /// streamed from a reader, or larger than this size, are not.
+    ///
+    /// Defaults to `None` (not retained).
+    pub retain_request_body: Option<usize>,
+
+    /// Collapse concurrent identical GET requests into one.
+    ///
+    /// When set, a GET request to a URI that already has a GET in flight from this agent
+    /// waits for that request and shares its response, instead of making another request.
+    /// The value is the max body size to share. The body is read to memory to share it.
+    ///
+    /// Only successful responses with a `content-length` up to this size, that aren't
+    /// marked `Cache-Control: no-store` or `private`, are shared. For other responses,
+    /// the waiting requests go on to make their own requests. Requests are matched on
+    /// the URI and all headers, so requests with different credentials never share a
+    /// response. A shared response has the [`ResponseExt`](crate::ResponseExt) details,
+    /// such as `elapsed()`, of the request that was made.
+    ///
+    /// Defaults to `None` (disabled).
+    pub single_flight: Option<usize>,
+
+    /// Called with each line of a response header, as it's received.
+    ///
+    /// The status line is included, line endings are not. Lines are reported as soon
+    /// as they are complete, before the whole header is parsed, which helps debugging
+    /// servers that send malformed headers, or send them slowly. This does not affect
+    /// the parsing of the response.
+    ///
+    /// Defaults to `None`.
+    pub on_header_line: Option<Arc<HeaderLineFn>>,
+
     /// Middleware used for this agent.
     ///
     /// Defaults to no middleware.
@@ -213,6 +299,63 @@ pub struct Timeouts {
     pub _must_use_default: private::Private,
 }
 
+/// Strategy for keeping `authorization` headers during redirects.
+///
+/// The header is never kept when a redirect goes from `https` to `http`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RedirectAuthHeaders {
+    /// Never preserve the `authorization` header on redirect.
+    Never,
+    /// Preserve the `authorization` header when the redirect is to the same host. Both hosts
+    /// must use the same scheme (or switch to a more secure one, i.e we can redirect from
+    /// `http` to `https`, but not the reverse). The port may differ.
+    SameHost,
+    /// Preserve the `authorization` header when the redirect is to the same origin,
+    /// i.e. the same scheme, host and port. This is the default.
+    SameOrigin,
+    /// Preserve the `authorization` header when the redirect is to one of the listed
+    /// hosts (or the same origin). Host names are compared case insensitively.
+    Allowlist(Vec<String>),
+}
+
+impl RedirectAuthHeaders {
+    /// Whether the `authorization` header may follow a redirect from `prev` to `next`.
+    pub(crate) fn keep(&self, prev: &Uri, next: &Uri) -> bool {
+        let downgrade = prev.scheme() == Some(&Scheme::HTTPS) && next.scheme() != prev.scheme();
+        if downgrade {
+            return false;
+        }
+
+        let same_host = match (prev.host(), next.host()) {
+            (Some(p), Some(n)) => p.eq_ignore_ascii_case(n),
+            _ => false,
+        };
+        let same_origin = same_host && prev.scheme() == next.scheme() && port(prev) == port(next);
+
+        match self {
+            RedirectAuthHeaders::Never => false,
+            RedirectAuthHeaders::SameHost => same_host,
+            RedirectAuthHeaders::SameOrigin => same_origin,
+            RedirectAuthHeaders::Allowlist(hosts) => {
+                same_origin
+                    || next
+                        .host()
+                        .map(|h| hosts.iter().any(|v| v.eq_ignore_ascii_case(h)))
+                        .unwrap_or(false)
+            }
+        }
+    }
+}
+
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("https") => Some(443),
+        Some("http") => Some(80),
+        _ => None,
+    })
+}
+
 // Deliberately not publicly visible.
 mod private {
     #[derive(Debug, Clone, Copy)]
@@ -240,17 +383,24 @@ impl Default for AgentConfig {
             #[cfg(feature = "_tls")]
             tls_config: TlsConfig::default(),
             proxy: Proxy::try_from_env(),
+            proxy_fallback_direct: false,
             no_delay: true,
             max_redirects: 10,
-            redirect_auth_headers: RedirectAuthHeaders::Never,
+            redirect_auth_headers: RedirectAuthHeaders::SameOrigin,
             user_agent: "ureq".to_string(), // TODO(martin): add version
             timeouts: Timeouts::default(),
             max_response_header_size: 64 * 1024,
+            reject_ambiguous_framing: false,
             input_buffer_size: 128 * 1024,
             output_buffer_size: 128 * 1024,
+            max_decompressed_size: None,
             max_idle_connections: 10,
             max_idle_connections_per_host: 3,
             max_idle_age: Duration::from_secs(15),
+            max_concurrent_per_host: None,
+            retain_request_body: None,
+            single_flight: None,
+            on_header_line: None,
             middleware: MiddlewareChain::default(),
 
             _must_use_default: private::Private,
@@ -288,13 +438,20 @@ impl fmt::Debug for AgentConfig {
             .field("user_agent", &self.user_agent)
             .field("input_buffer_size", &self.input_buffer_size)
             .field("output_buffer_size", &self.output_buffer_size)
+            .field("reject_ambiguous_framing", &self.reject_ambiguous_framing)
+            .field("max_decompressed_size", &self.max_decompressed_size)
             .field("max_idle_connections", &self.max_idle_connections)
             .field(
                 "max_idle_connections_per_host",
                 &self.max_idle_connections_per_host,
             )
             .field("max_idle_age", &self.max_idle_age)
-            .field("proxy", &self.proxy);
+            .field("max_concurrent_per_host", &self.max_concurrent_per_host)
+            .field("retain_request_body", &self.retain_request_body)
+            .field("single_flight", &self.single_flight)
+            .field("on_header_line", &self.on_header_line.is_some())
+            .field("proxy", &self.proxy)
+            .field("proxy_fallback_direct", &self.proxy_fallback_direct);
 
         #[cfg(feature = "_tls")]
         {
@@ -320,3 +477,31 @@ impl fmt::Debug for Timeouts {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redirect_auth_headers_keep() {
+        let uri = |s: &str| s.parse::<Uri>().unwrap();
+        let a = uri("https://a.test/x");
+
+        let same_origin = RedirectAuthHeaders::SameOrigin;
+        assert!(same_origin.keep(&a, &uri("https://A.test:443/y")));
+        assert!(!same_origin.keep(&a, &uri("https://a.test:8443/y")));
+        assert!(!same_origin.keep(&a, &uri("https://b.test/y")));
+
+        let same_host = RedirectAuthHeaders::SameHost;
+        assert!(same_host.keep(&a, &uri("https://a.test:8443/y")));
+        assert!(same_host.keep(&uri("http://a.test/x"), &a));
+        assert!(!same_host.keep(&a, &uri("http://a.test/y")));
+
+        let allow = RedirectAuthHeaders::Allowlist(vec!["b.test".into()]);
+        assert!(allow.keep(&a, &uri("https://b.test/y")));
+        assert!(!allow.keep(&a, &uri("http://b.test/y")));
+        assert!(!allow.keep(&a, &uri("https://c.test/y")));
+
+        assert!(!RedirectAuthHeaders::Never.keep(&a, &a));
+    }
+}
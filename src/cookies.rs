@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use std::io;
 use std::sync::{Mutex, MutexGuard};
 
-use cookie_store::CookieStore;
+use cookie_store::{CookieDomain, CookieStore};
 use http::Uri;
 
 use crate::util::UriExt;
@@ -11,14 +11,21 @@ use crate::Error;
 
 #[derive(Debug)]
 pub(crate) struct SharedCookieJar {
-    inner: Mutex<CookieStore>,
+    inner: Mutex<Jar>,
+}
+
+#[derive(Debug)]
+struct Jar {
+    store: CookieStore,
+    #[cfg(feature = "public-suffix")]
+    public_suffixes: Option<publicsuffix::List>,
 }
 
 /// Collection of cookies.
 ///
 /// The jar is accessed using [`Agent::cookie_jar`][crate::Agent::cookie_jar].
 /// It can be saved and loaded.
-pub struct CookieJar<'a>(MutexGuard<'a, CookieStore>);
+pub struct CookieJar<'a>(MutexGuard<'a, Jar>);
 
 /// Representation of an HTTP cookie.
 ///
@@ -94,6 +101,7 @@ impl<'a> CookieJar<'a> {
     /// `path`, and `name`.
     pub fn get(&self, domain: &str, path: &str, name: &str) -> Option<Cookie<'_>> {
         self.0
+            .store
             .get(domain, path, name)
             .map(|c| Cookie(CookieInner::Borrowed(c)))
     }
@@ -101,26 +109,44 @@ impl<'a> CookieJar<'a> {
     /// Removes a `Cookie` from the jar, returning the `Cookie` if it was in the jar
     pub fn remove(&mut self, domain: &str, path: &str, name: &str) -> Option<Cookie<'static>> {
         self.0
+            .store
             .remove(domain, path, name)
             .map(|c| Cookie(CookieInner::Owned(c)))
     }
 
     /// Inserts `cookie`, received from `uri`, into the jar, following the rules of the
     /// [IETF RFC6265 Storage Model](https://datatracker.ietf.org/doc/html/rfc6265#section-5.3).
+    ///
+    /// A cookie with a `Domain` attribute that is a public suffix is rejected, since it
+    /// would be sent to unrelated sites. The exception is when the public suffix is the
+    /// host of `uri`, in which case the cookie is only for that host. Top level domains,
+    /// such as `com`, are always public suffixes. Others, such as `co.uk`, need the list
+    /// from [`CookieJar::load_public_suffix_list`].
     pub fn insert(&mut self, cookie: Cookie<'static>, uri: &Uri) -> Result<(), Error> {
         let url = uri.try_into_url()?;
-        self.0.insert(cookie.into_owned(), &url)?;
+        let mut cookie = cookie.into_owned();
+
+        if self.0.is_public_suffix(&cookie.domain) {
+            if !cookie.domain.host_is_identical(&url) {
+                debug!("Reject cookie for public suffix: {:?}", cookie.domain);
+                return Err(Error::CookieValue("Cookie domain is a public suffix"));
+            }
+            cookie.domain = CookieDomain::host_only(&url)?;
+        }
+
+        self.0.store.insert(cookie, &url)?;
         Ok(())
     }
 
     /// Clear the contents of the jar
     pub fn clear(&mut self) {
-        self.0.clear()
+        self.0.store.clear()
     }
 
     /// An iterator visiting all the __unexpired__ cookies in the jar
     pub fn iter(&self) -> impl Iterator<Item = Cookie<'_>> {
         self.0
+            .store
             .iter_unexpired()
             .map(|c| Cookie(CookieInner::Borrowed(c)))
     }
@@ -128,23 +154,99 @@ impl<'a> CookieJar<'a> {
     /// Serialize any __unexpired__ and __persistent__ cookies in the jar to JSON format and
     /// write them to `writer`
     pub fn save_json<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
-        Ok(self.0.save_json(writer)?)
+        Ok(self.0.store.save_json(writer)?)
     }
 
     /// Load JSON-formatted cookies from `reader`, skipping any __expired__ cookies
     ///
-    /// Replaces all the contents of the current cookie jar.
+    /// Replaces all the contents of the current cookie jar. Cookies with a `Domain`
+    /// that is a public suffix are left out, like for [`CookieJar::insert`].
     pub fn load_json<R: io::BufRead>(&mut self, reader: R) -> Result<(), Error> {
-        let store = CookieStore::load_json(reader)?;
-        *self.0 = store;
+        self.0.store = CookieStore::load_json(reader)?;
+        self.0.remove_public_suffixes();
+        Ok(())
+    }
+
+    /// Load the [Public Suffix List](https://publicsuffix.org/list/) used to reject cookies.
+    ///
+    /// Requires the **public-suffix** feature.
+    ///
+    /// The list is read in the format of `public_suffix_list.dat`. Without it, only top
+    /// level domains are treated as public suffixes. Cookies already in the jar with a
+    /// `Domain` that is on the list are removed.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// let agent = ureq::agent();
+    ///
+    /// let file = File::open("public_suffix_list.dat")?;
+    /// agent.cookie_jar().load_public_suffix_list(file)?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "public-suffix")]
+    pub fn load_public_suffix_list<R: io::Read>(&mut self, mut reader: R) -> Result<(), Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let list = publicsuffix::List::from_bytes(&data)
+            .map_err(|_| Error::CookieValue("Invalid public suffix list"))?;
+
+        self.0.public_suffixes = Some(list);
+        self.0.remove_public_suffixes();
         Ok(())
     }
 }
 
+impl Jar {
+    /// Whether the cookie's `Domain` attribute is a public suffix.
+    ///
+    /// Host only cookies have no `Domain` attribute and are never for a public suffix.
+    fn is_public_suffix(&self, domain: &CookieDomain) -> bool {
+        let CookieDomain::Suffix(suffix) = domain else {
+            return false;
+        };
+
+        // Top level domains are public suffixes, list or not.
+        if !suffix.trim_matches('.').contains('.') {
+            return true;
+        }
+
+        #[cfg(feature = "public-suffix")]
+        if let Some(list) = &self.public_suffixes {
+            return domain.is_public_suffix(list);
+        }
+
+        false
+    }
+
+    fn remove_public_suffixes(&mut self) {
+        let remove: Vec<_> = self
+            .store
+            .iter_any()
+            .filter(|c| self.is_public_suffix(&c.domain))
+            .map(|c| {
+                let domain = c.domain.as_cow().unwrap_or_default().into_owned();
+                let path = c.path.as_ref().to_string();
+                (domain, path, c.name().to_string())
+            })
+            .collect();
+
+        for (domain, path, name) in remove {
+            debug!("Remove cookie for public suffix: {}", domain);
+            self.store.remove(&domain, &path, &name);
+        }
+    }
+}
+
 impl SharedCookieJar {
     pub(crate) fn new() -> Self {
         SharedCookieJar {
-            inner: Mutex::new(CookieStore::new()),
+            inner: Mutex::new(Jar {
+                store: CookieStore::default(),
+                #[cfg(feature = "public-suffix")]
+                public_suffixes: None,
+            }),
         }
     }
 
@@ -164,9 +266,9 @@ impl SharedCookieJar {
             }
         };
 
-        let store = self.inner.lock().unwrap();
+        let jar = self.inner.lock().unwrap();
 
-        for c in store.matches(&url) {
+        for c in jar.store.matches(&url) {
             if !is_cookie_rfc_compliant(c) {
                 debug!("Do not send non compliant cookie: {:?}", c.name());
                 continue;
@@ -287,6 +389,83 @@ mod test {
         assert!(!is_cookie_rfc_compliant(cookie.as_cookie_store()));
     }
 
+    fn insert(jar: &mut CookieJar, cookie: &str, uri: &str) -> Result<(), Error> {
+        let uri = Uri::try_from(uri).unwrap();
+        let cookie = Cookie::parse(cookie.to_string(), &uri).unwrap();
+        jar.insert(cookie, &uri)
+    }
+
+    #[test]
+    fn reject_top_level_domain() {
+        let jar = SharedCookieJar::new();
+        let mut jar = jar.lock();
+
+        let err = insert(&mut jar, "a=1; Domain=.com", "https://example.com").unwrap_err();
+        assert!(matches!(err, Error::CookieValue(_)));
+
+        insert(
+            &mut jar,
+            "b=2; Domain=example.com",
+            "https://www.example.com",
+        )
+        .unwrap();
+        assert!(jar.get("example.com", "/", "b").is_some());
+
+        // Identical to the host means host only.
+        insert(&mut jar, "c=3; Domain=localhost", "http://localhost").unwrap();
+        assert!(jar.get("localhost", "/", "c").is_some());
+        assert_eq!(jar.iter().count(), 2);
+    }
+
+    #[test]
+    fn load_json_leaves_out_top_level_domain() {
+        let json = r#"{"raw_cookie":"a=1; Domain=com","path":["/",false],"domain":{"Suffix":"com"},"expires":{"AtUtc":"2100-01-01T00:00:00Z"}}
+{"raw_cookie":"b=2; Domain=example.com","path":["/",false],"domain":{"Suffix":"example.com"},"expires":{"AtUtc":"2100-01-01T00:00:00Z"}}
+"#;
+
+        let jar = SharedCookieJar::new();
+        let mut jar = jar.lock();
+        jar.load_json(json.as_bytes()).unwrap();
+
+        assert!(jar.get("com", "/", "a").is_none());
+        assert!(jar.get("example.com", "/", "b").is_some());
+    }
+
+    #[cfg(feature = "public-suffix")]
+    #[test]
+    fn reject_public_suffix_domain() {
+        const LIST: &str = "// ===BEGIN ICANN DOMAINS===\nuk\nco.uk\n// ===END ICANN DOMAINS===\n";
+
+        let jar = SharedCookieJar::new();
+        let mut jar = jar.lock();
+
+        // Without the list, co.uk is like any other domain.
+        insert(&mut jar, "a=1; Domain=co.uk", "https://shop.co.uk").unwrap();
+        assert!(jar.get("co.uk", "/", "a").is_some());
+
+        jar.load_public_suffix_list(LIST.as_bytes()).unwrap();
+        assert!(jar.get("co.uk", "/", "a").is_none());
+
+        let err = insert(&mut jar, "a=1; Domain=co.uk", "https://shop.co.uk").unwrap_err();
+        assert!(matches!(err, Error::CookieValue(_)));
+
+        insert(&mut jar, "b=2; Domain=shop.co.uk", "https://www.shop.co.uk").unwrap();
+        assert!(jar.get("shop.co.uk", "/", "b").is_some());
+
+        // Identical to the host means host only.
+        insert(&mut jar, "c=3; Domain=co.uk", "https://co.uk").unwrap();
+        assert!(jar.get("co.uk", "/", "c").is_some());
+        assert_eq!(jar.iter().count(), 2);
+
+        // Loading keeps the list.
+        let mut json = vec![];
+        jar.save_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap()
+            + r#"{"raw_cookie":"d=4; Domain=co.uk","path":["/",false],"domain":{"Suffix":"co.uk"},"expires":{"AtUtc":"2100-01-01T00:00:00Z"}}"#;
+        jar.load_json(json.as_bytes()).unwrap();
+        assert!(jar.get("co.uk", "/", "d").is_none());
+    }
+
     #[test]
     fn legal_cookie_name_value() {
         let cookie = Cookie::parse("name=value", &uri()).unwrap();
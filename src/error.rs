@@ -30,6 +30,12 @@ pub enum Error {
     #[error("protocol: {0}")]
     Protocol(#[from] hoot::Error),
 
+    /// The response status line is malformed.
+    ///
+    /// The value is the status line as sent by the server, cut off after 100 bytes.
+    #[error("bad status line: {0}")]
+    BadStatus(String),
+
     /// Error in io such as the TCP socket.
     #[error("io: {0}")]
     Io(io::Error),
@@ -148,11 +154,42 @@ pub enum Error {
     #[cfg(any(feature = "gzip", feature = "brotli"))]
     Decompress(&'static str, io::Error),
 
+    /// The decompressed body is larger than
+    /// [`AgentConfig::max_decompressed_size`](crate::AgentConfig::max_decompressed_size).
+    #[error("decompressed body is larger than limit: {0}")]
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    DecompressExceedsLimit(u64),
+
     /// Serde JSON error.
+    ///
+    /// For invalid JSON, the position of the error is available with
+    /// [`serde_json::Error::line()`] and [`serde_json::Error::column()`]. Errors reading
+    /// the body are not turned into this variant, a timeout is still [`Error::Timeout`].
     #[cfg(feature = "json")]
     #[error("json: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// The server responded `101 Switching Protocols`.
+    ///
+    /// ureq does not support protocol upgrades. After such a response the connection
+    /// no longer speaks HTTP/1.1, so the response can't be read as a regular response.
+    #[error("server switched protocols (101), which is not supported")]
+    UnsupportedUpgrade,
+
+    /// The response body doesn't match the `Digest` or `Content-MD5` header.
+    ///
+    /// The value is the digest algorithm, `md5` for `Content-MD5`, or `None` if there is
+    /// no `Digest` header with a supported algorithm and no `Content-MD5` header.
+    #[cfg(feature = "digest")]
+    #[error("content digest mismatch: {}", .0.unwrap_or("no supported digest header"))]
+    ContentDigest(Option<&'static str>),
+
+    /// A partial response could not be completed with further range requests.
+    ///
+    /// See [`RequestBuilder::auto_complete_partial`](crate::RequestBuilder::auto_complete_partial).
+    #[error("partial content not completed: {0}")]
+    PartialContent(&'static str),
+
     /// Attempt to connect to a CONNECT proxy failed.
     #[error("CONNECT proxy failed: {0}")]
     ConnectProxyFailed(String),
@@ -168,19 +205,81 @@ pub enum Error {
 impl Error {
     /// Convert the error into a [`std::io::Error`].
     ///
-    /// If the error is [`Error::Io`], we unpack the error. In othe cases we make
+    /// If the error is [`Error::Io`], we unpack the error. [`Error::BodyExceedsLimit`]
+    /// becomes an `std::io::ErrorKind::InvalidData`. In othe cases we make
     /// an `std::io::ErrorKind::Other`.
     pub fn into_io(self) -> io::Error {
-        if let Self::Io(e) = self {
-            e
-        } else {
-            io::Error::new(io::ErrorKind::Other, self)
+        match self {
+            Self::Io(e) => e,
+            Self::BodyExceedsLimit(_) => io::Error::new(io::ErrorKind::InvalidData, self),
+            _ => io::Error::new(io::ErrorKind::Other, self),
+        }
+    }
+
+    /// Tell if the error is a timeout.
+    ///
+    /// This is [`Error::Timeout`], or an [`Error::Io`] of kind `TimedOut`.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Error::Timeout(_) => true,
+            Error::Io(e) => e.kind() == io::ErrorKind::TimedOut,
+            _ => false,
         }
     }
 
+    /// Tell if the error is about failing to connect, or losing the connection.
+    ///
+    /// This covers not finding the host, failing to connect (directly or via proxy) and
+    /// [`Error::Io`] where the connection is refused, reset, aborted or unexpectedly closed.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            Error::HostNotFound | Error::ConnectionFailed | Error::ConnectProxyFailed(_) => true,
+            Error::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        }
+    }
+
+    /// Tell if the error comes from TLS, or the certificates/keys used for TLS.
+    pub fn is_tls_error(&self) -> bool {
+        match self {
+            #[cfg(feature = "_tls")]
+            Error::Tls(_) | Error::Pem(_) => true,
+            #[cfg(feature = "rustls")]
+            Error::Rustls(_) => true,
+            #[cfg(feature = "native-tls")]
+            Error::NativeTls(_) | Error::Der(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Tell if the error is an [`Error::Io`].
+    pub fn is_io(&self) -> bool {
+        matches!(self, Error::Io(_))
+    }
+
     pub(crate) fn disconnected() -> Error {
         io::Error::new(io::ErrorKind::UnexpectedEof, "Peer disconnected").into()
     }
+
+    /// The connection closed before the end of a content-length or chunked body.
+    ///
+    /// This is `UnexpectedEof` rather than `InvalidData`: the data received is fine, it's
+    /// the rest that is missing. It's also the kind `is_connection_error()` knows.
+    pub(crate) fn body_truncated() -> Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "response body closed before all bytes were read",
+        )
+        .into()
+    }
 }
 
 /// Motivation for an [`Error::Timeout`].
@@ -272,6 +371,30 @@ mod test {
         assert!(matches!(err, Error::StatusCode(500)));
     }
 
+    #[test]
+    fn error_predicates() {
+        let timeout = Error::Timeout(TimeoutReason::Global);
+        assert!(timeout.is_timeout());
+        assert!(!timeout.is_connection_error());
+        assert!(!timeout.is_io());
+
+        let io_timeout = Error::Io(io::Error::new(io::ErrorKind::TimedOut, "slow"));
+        assert!(io_timeout.is_timeout());
+        assert!(io_timeout.is_io());
+
+        assert!(Error::HostNotFound.is_connection_error());
+        assert!(Error::ConnectionFailed.is_connection_error());
+        assert!(Error::disconnected().is_connection_error());
+        let refused = Error::Io(io::Error::new(io::ErrorKind::ConnectionRefused, "no"));
+        assert!(refused.is_connection_error());
+        assert!(!refused.is_timeout());
+
+        #[cfg(feature = "_tls")]
+        assert!(Error::Tls("bad").is_tls_error());
+        assert!(!Error::StatusCode(500).is_tls_error());
+        assert!(!Error::StatusCode(500).is_io());
+    }
+
     #[test]
     fn ensure_error_size() {
         // This is platform dependent, so we can't be too strict or precise.
@@ -151,14 +151,19 @@
 //!   a default or used by the crate level convenience calls (`ureq::get` etc) – it must be configured
 //!   on the agent.
 //! * **socks-proxy** enables proxy config using the `socks4://`, `socks4a://`, `socks5://`
-//!    and `socks://` (equal to `socks5://`) prefix.
+//!   and `socks://` (equal to `socks5://`) prefix.
 //! * **cookies** enables cookies.
+//! * **public-suffix** enables rejecting cookies for domains on the
+//!   [Public Suffix List](https://publicsuffix.org), implies **cookies**.
 //! * **gzip** enables requests of gzip-compressed responses and decompresses them.
+//!   Responses using `deflate` are decompressed as well.
 //! * **brotli** enables requests brotli-compressed responses and decompresses them.
 //! * **charset** enables interpreting the charset part of the Content-Type header
-//!    (e.g.  `Content-Type: text/plain; charset=iso-8859-1`). Without this, the
-//!    library defaults to Rust's built in `utf-8`.
+//!   (e.g.  `Content-Type: text/plain; charset=iso-8859-1`). Without this, the
+//!   library defaults to Rust's built in `utf-8`.
 //! * **json** enables JSON sending and receiving via serde_json.
+//! * **digest** enables verifying the response body against a `Digest` header
+//!   (sha-256, sha-512 or md5) or a `Content-MD5` header.
 //!
 //! # JSON
 //!
@@ -322,23 +327,28 @@ use std::convert::TryFrom;
 /// Re-exported http-crate.
 pub use http;
 
-pub use body::{Body, BodyReader, BodyWithConfig};
-pub use config::{AgentConfig, Timeouts};
+pub use body::{Body, BodyBuilder, BodyReader, BodyWithConfig};
+pub use config::{AgentConfig, RedirectAuthHeaders, Timeouts};
 use http::Method;
 use http::{Request, Response, Uri};
 pub use proxy::Proxy;
-pub use request::RequestBuilder;
+pub use request::{RequestBuilder, RequestTemplate};
 use request::{WithBody, WithoutBody};
+pub use response::{ResponseExt, StatusCategory};
 pub use send_body::AsSendBody;
 
 mod agent;
+mod auth;
 mod body;
 mod config;
 mod error;
+mod partial;
 mod pool;
 mod proxy;
 mod request;
+mod response;
 mod send_body;
+mod single_flight;
 mod unit;
 mod util;
 
@@ -355,6 +365,7 @@ mod cookies;
 pub use cookies::{Cookie, CookieJar};
 
 pub use agent::Agent;
+pub use auth::AuthChallenge;
 pub use error::{Error, TimeoutReason};
 pub use send_body::SendBody;
 
@@ -407,6 +418,468 @@ pub(crate) mod test {
         *INIT_LOG
     }
 
+    #[test]
+    fn scoped_agent_shares_pool() {
+        init_test_log();
+        crate::transport::set_handler("/status/500", 500, &[("content-length", "0")], &[]);
+
+        let agent = Agent::new_with_defaults();
+        let scoped = agent.scoped(|c| c.http_status_as_error = false);
+
+        let err = agent.get("https://my.test/status/500").call().unwrap_err();
+        assert!(matches!(err, Error::StatusCode(500)));
+
+        let mut res = scoped.get("https://my.test/status/500").call().unwrap();
+        assert_eq!(res.status(), 500);
+        res.body_mut().read_to_vec().unwrap();
+
+        // The connection from the scoped agent went back to the shared pool.
+        assert_eq!(agent.pool_count(), 1);
+    }
+
+    #[test]
+    fn redirect_auth_headers_policy() {
+        init_test_log();
+        // Close, since the test server handles one request per connection.
+        let close = ("connection", "close");
+        crate::transport::set_handler(
+            "/redirect_same",
+            302,
+            &[("location", "https://my.test/echo_headers"), close],
+            &[],
+        );
+        crate::transport::set_handler(
+            "/redirect_other",
+            302,
+            &[("location", "https://other.test/echo_headers"), close],
+            &[],
+        );
+
+        // A new agent per call, to not reuse pooled connections.
+        let call = |config: AgentConfig, uri: &str| {
+            Agent::new_with_config(config)
+                .get(uri)
+                .header("authorization", "secret")
+                .call()
+                .unwrap()
+                .body_mut()
+                .read_to_string()
+                .unwrap()
+        };
+
+        let body = call(AgentConfig::default(), "https://my.test/redirect_same");
+        assert!(body.contains("authorization: secret"));
+
+        let body = call(AgentConfig::default(), "https://my.test/redirect_other");
+        assert!(!body.contains("authorization"));
+
+        let config = AgentConfig {
+            redirect_auth_headers: RedirectAuthHeaders::Allowlist(vec!["Other.test".into()]),
+            ..Default::default()
+        };
+        let body = call(config, "https://my.test/redirect_other");
+        assert!(body.contains("authorization: secret"));
+
+        // Once dropped, a same origin hop on the other host doesn't bring it back.
+        crate::transport::set_handler(
+            "/redirect_leave",
+            302,
+            &[("location", "https://evil.test/redirect_stay"), close],
+            &[],
+        );
+        crate::transport::set_handler(
+            "/redirect_stay",
+            302,
+            &[("location", "https://evil.test/echo_headers"), close],
+            &[],
+        );
+        let body = call(AgentConfig::default(), "https://my.test/redirect_leave");
+        assert!(!body.contains("authorization"));
+    }
+
+    #[test]
+    fn redirect_to_get_leaves_out_body_headers() {
+        use crate::transport::{last_request, record_requests};
+
+        init_test_log();
+        record_requests();
+        crate::transport::set_handler(
+            "/see_other",
+            303,
+            &[
+                ("location", "https://my.test/echo_headers"),
+                ("connection", "close"),
+            ],
+            &[],
+        );
+
+        for auth in [false, true] {
+            let mut req = Agent::new_with_defaults()
+                .post("https://my.test/see_other")
+                .header("content-type", "application/json")
+                .header("x-custom", "yes");
+            if auth {
+                req = req.header("authorization", "secret");
+            }
+            req.send("{}").unwrap();
+
+            let req = last_request().unwrap();
+            assert_eq!(req.method, http::Method::GET);
+            assert!(req.headers.get("content-type").is_none());
+            assert_eq!(req.headers.get("x-custom").unwrap(), "yes");
+            assert_eq!(req.headers.contains_key("authorization"), auth);
+        }
+    }
+
+    #[test]
+    fn proxy_fallback_direct() {
+        use std::io;
+
+        use crate::proxy::ProxyUnreachable;
+        use crate::resolver::DefaultResolver;
+        use crate::transport::{ConnectionDetails, Connector, DefaultConnector, Transport};
+
+        init_test_log();
+
+        // Connecting through the proxy fails, either as if it was down, or
+        // with some error once connected to it, like TLS.
+        #[derive(Debug)]
+        struct ProxyFails(DefaultConnector, bool);
+
+        impl Connector for ProxyFails {
+            fn connect(
+                &self,
+                details: &ConnectionDetails,
+                chained: Option<Box<dyn Transport>>,
+            ) -> Result<Option<Box<dyn Transport>>, Error> {
+                if details.config.proxy.is_some() {
+                    let err = Error::Io(io::ErrorKind::ConnectionRefused.into());
+                    // This is what TcpConnector does when connecting to the proxy fails.
+                    return Err(if self.1 {
+                        ProxyUnreachable::wrap(err)
+                    } else {
+                        err
+                    });
+                }
+                self.0.connect(details, chained)
+            }
+        }
+
+        let agent = |fallback: bool, down: bool| {
+            let config = AgentConfig {
+                proxy: Some(Proxy::new("http://proxy.test:3128").unwrap()),
+                proxy_fallback_direct: fallback,
+                ..Default::default()
+            };
+            Agent::with_parts(
+                config,
+                ProxyFails(DefaultConnector::new(), down && fallback),
+                DefaultResolver::default(),
+            )
+        };
+
+        let err = agent(false, true)
+            .get("https://my.test/get")
+            .call()
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+
+        let res = agent(true, true).get("https://my.test/get").call().unwrap();
+        assert_eq!(res.status(), 200);
+
+        // The proxy was reached, the failure is after that.
+        let err = agent(true, false)
+            .get("https://my.test/get")
+            .call()
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn proxy_fallback_direct_unresolved() {
+        use crate::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+        use crate::transport::time::NextTimeout;
+        use crate::transport::DefaultConnector;
+
+        init_test_log();
+
+        // The proxy host doesn't resolve.
+        #[derive(Debug)]
+        struct NoProxyHost;
+
+        impl Resolver for NoProxyHost {
+            fn resolve(
+                &self,
+                uri: &Uri,
+                config: &AgentConfig,
+                timeout: NextTimeout,
+            ) -> Result<ResolvedSocketAddrs, Error> {
+                if uri.host() == Some("proxy.test") {
+                    return Err(Error::HostNotFound);
+                }
+                DefaultResolver::default().resolve(uri, config, timeout)
+            }
+        }
+
+        let agent = |fallback: bool| {
+            let config = AgentConfig {
+                proxy: Some(Proxy::new("http://proxy.test:3128").unwrap()),
+                proxy_fallback_direct: fallback,
+                ..Default::default()
+            };
+            Agent::with_parts(config, DefaultConnector::new(), NoProxyHost)
+        };
+
+        let err = agent(false).get("https://my.test/get").call().unwrap_err();
+        assert!(matches!(err, Error::HostNotFound));
+
+        let res = agent(true).get("https://my.test/get").call().unwrap();
+        assert_eq!(res.status(), 200);
+    }
+
+    #[test]
+    fn proxy_fallback_direct_per_redirect() {
+        use std::io;
+        use std::net::SocketAddr;
+        use std::sync::{Arc, Mutex};
+
+        use crate::proxy::ProxyUnreachable;
+        use crate::resolver::{DefaultResolver, ResolvedSocketAddrs, Resolver};
+        use crate::transport::time::NextTimeout;
+        use crate::transport::{ConnectionDetails, Connector, DefaultConnector, Transport};
+
+        init_test_log();
+        crate::transport::set_handler(
+            "/redirect_proxy",
+            302,
+            &[("location", "https://my.test/get"), ("connection", "close")],
+            &[],
+        );
+
+        let proxy_addr: SocketAddr = "127.0.0.1:3128".parse().unwrap();
+
+        // The proxy host only resolves on the second try.
+        #[derive(Debug)]
+        struct ProxyHostLater(Mutex<bool>, SocketAddr);
+
+        impl Resolver for ProxyHostLater {
+            fn resolve(
+                &self,
+                uri: &Uri,
+                config: &AgentConfig,
+                timeout: NextTimeout,
+            ) -> Result<ResolvedSocketAddrs, Error> {
+                if uri.host() == Some("proxy.test") {
+                    let mut tried = self.0.lock().unwrap();
+                    if !*tried {
+                        *tried = true;
+                        return Err(Error::HostNotFound);
+                    }
+                    return Ok(std::iter::once(self.1).collect());
+                }
+                DefaultResolver::default().resolve(uri, config, timeout)
+            }
+        }
+
+        // Counts the attempts to connect to the proxy, which is unreachable.
+        #[derive(Debug)]
+        struct ProxyConnector(Arc<Mutex<usize>>, SocketAddr);
+
+        impl Connector for ProxyConnector {
+            fn connect(
+                &self,
+                details: &ConnectionDetails,
+                chained: Option<Box<dyn Transport>>,
+            ) -> Result<Option<Box<dyn Transport>>, Error> {
+                if details.addrs.contains(&self.1) {
+                    *self.0.lock().unwrap() += 1;
+                    let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+                    return Err(ProxyUnreachable::wrap(Error::Io(refused)));
+                }
+                DefaultConnector::new().connect(details, chained)
+            }
+        }
+
+        let attempts = Arc::new(Mutex::new(0));
+        let config = AgentConfig {
+            proxy: Some(Proxy::new("http://proxy.test:3128").unwrap()),
+            proxy_fallback_direct: true,
+            ..Default::default()
+        };
+        let agent = Agent::with_parts(
+            config,
+            ProxyConnector(attempts.clone(), proxy_addr),
+            ProxyHostLater(Mutex::new(false), proxy_addr),
+        );
+
+        let res = agent.get("https://my.test/redirect_proxy").call().unwrap();
+        assert_eq!(res.status(), 200);
+
+        // The first hop went direct since the proxy didn't resolve, the redirect
+        // tried the proxy again.
+        assert_eq!(*attempts.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn reject_ambiguous_framing() {
+        init_test_log();
+        crate::transport::set_handler(
+            "/ambiguous",
+            200,
+            &[("transfer-encoding", "chunked"), ("content-length", "100")],
+            b"5\r\nhello\r\n0\r\n\r\n",
+        );
+
+        // Chunked wins by default.
+        let mut res = crate::get("https://my.test/ambiguous").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+
+        let agent: Agent = AgentConfig {
+            reject_ambiguous_framing: true,
+            ..Default::default()
+        }
+        .into();
+        let err = agent.get("https://my.test/ambiguous").call().unwrap_err();
+        assert!(matches!(err, Error::Protocol(hoot::Error::BadHeader(_))));
+    }
+
+    #[test]
+    fn bare_lf_line_endings() {
+        init_test_log();
+        let mut res = get("https://my.test/bare-lf").call().unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers().get("content-type").unwrap(), "text/plain");
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn status_line_without_reason() {
+        init_test_log();
+        let mut res = get("https://my.test/no-reason").call().unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn bad_status_has_line() {
+        init_test_log();
+        let err = get("https://my.test/bad-status").call().unwrap_err();
+        assert!(matches!(err, Error::BadStatus(_)));
+        assert!(err.to_string().contains("BORKED"));
+    }
+
+    #[test]
+    fn ambiguous_framing_drops_content_length() {
+        init_test_log();
+        // A smuggled second response hides after the chunked body, within the
+        // content-length.
+        crate::transport::set_handler(
+            "/smuggle",
+            200,
+            &[("transfer-encoding", "chunked"), ("content-length", "64")],
+            b"5\r\nhello\r\n0\r\n\r\nHTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nevil!",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let mut res = agent.get("https://my.test/smuggle").call().unwrap();
+        assert!(!res.headers().contains_key("content-length"));
+        assert_eq!(res.content_length(), None);
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+
+        // The connection is not reused.
+        assert_eq!(agent.pool_count(), 0);
+    }
+
+    #[test]
+    fn non_ascii_header_value_bytes() {
+        init_test_log();
+        crate::transport::set_handler(
+            "/non_ascii_header",
+            200,
+            &[
+                (
+                    "content-disposition",
+                    "attachment; filename=\"caf\u{e9}.txt\"",
+                ),
+                ("content-length", "0"),
+            ],
+            b"",
+        );
+
+        let res = crate::get("https://my.test/non_ascii_header")
+            .call()
+            .unwrap();
+        let value = res.headers().get("content-disposition").unwrap();
+
+        // Not a valid &str view, but the raw bytes are kept.
+        assert!(value.to_str().is_err());
+        assert_eq!(
+            value.as_bytes(),
+            "attachment; filename=\"caf\u{e9}.txt\"".as_bytes()
+        );
+    }
+
+    #[test]
+    fn repeated_header_bytes_in_order() {
+        init_test_log();
+        crate::transport::set_handler(
+            "/repeated_header",
+            200,
+            &[
+                ("x-multi", "first"),
+                ("x-multi", "caf\u{e9}"),
+                ("x-multi", "last"),
+                ("content-length", "0"),
+            ],
+            b"",
+        );
+
+        let res = crate::get("https://my.test/repeated_header")
+            .call()
+            .unwrap();
+        let values: Vec<&[u8]> = res
+            .headers()
+            .get_all("x-multi")
+            .iter()
+            .map(|v| v.as_bytes())
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![&b"first"[..], "caf\u{e9}".as_bytes(), &b"last"[..]]
+        );
+    }
+
+    #[test]
+    fn on_header_line_reports_raw_lines() {
+        init_test_log();
+        crate::transport::set_handler(
+            "/header_lines",
+            200,
+            &[("x-custom", "one"), ("content-length", "4")],
+            b"body",
+        );
+
+        let lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let captured = lines.clone();
+
+        let agent: Agent = AgentConfig {
+            on_header_line: Some(std::sync::Arc::new(move |line: &str| {
+                captured.lock().unwrap().push(line.to_string())
+            })),
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/header_lines").call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "body");
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines[0], "HTTP/1.1 200 OK");
+        assert!(lines.iter().any(|l| l == "x-custom: one"));
+        assert!(!lines.iter().any(|l| l.is_empty() || l.contains("body")));
+    }
+
     #[test]
     fn connect_http_google() {
         init_test_log();
@@ -548,6 +1021,13 @@ pub(crate) mod test {
         res.body_mut().read_to_string().unwrap();
     }
 
+    #[test]
+    fn response_version_is_typed() {
+        init_test_log();
+        let res = get("http://httpbin.org/get").call().unwrap();
+        assert_eq!(res.version(), http::Version::HTTP_11);
+    }
+
     #[test]
     fn connect_https_invalid_name() {
         let result = get("https://example.com{REQUEST_URI}/").call();
@@ -3,6 +3,7 @@
 use core::fmt;
 use std::sync::Arc;
 
+use crate::partial::RangeContinuation;
 use crate::{Agent, Body, Error, SendBody};
 
 /// Chained processing of request (and response).
@@ -183,6 +184,59 @@ impl MiddlewareChain {
 
         chain.push(Box::new(mw));
     }
+
+    /// Add a hook that inspects the response once the status and headers are received.
+    ///
+    /// The hook runs before the body is read, for the final response after any redirects.
+    /// It doesn't run for the range requests made to complete a partial response, see
+    /// [`RequestBuilder::auto_complete_partial()`](crate::RequestBuilder::auto_complete_partial).
+    /// Returning an error fails the request, which is useful to enforce policies early,
+    /// such as rejecting unexpected content types.
+    ///
+    /// ```
+    /// use std::io;
+    /// use ureq::{Agent, AgentConfig, Error};
+    ///
+    /// let mut config = AgentConfig::default();
+    /// config.middleware.after_headers(|res| {
+    ///     if res.body().mime_type() != Some("application/json") {
+    ///         return Err(Error::Io(io::Error::new(io::ErrorKind::Other, "not json")));
+    ///     }
+    ///     Ok(())
+    /// });
+    ///
+    /// let agent: Agent = config.into();
+    ///
+    /// assert!(agent.get("http://httpbin.org/html").call().is_err());
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn after_headers<F>(&mut self, f: F)
+    where
+        F: Fn(&http::Response<Body>) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.add(AfterHeaders(f));
+    }
+}
+
+struct AfterHeaders<F>(F);
+
+impl<F> Middleware for AfterHeaders<F>
+where
+    F: Fn(&http::Response<Body>) -> Result<(), Error> + Send + Sync + 'static,
+{
+    fn handle(
+        &self,
+        request: http::Request<SendBody>,
+        next: MiddlewareNext,
+    ) -> Result<http::Response<Body>, Error> {
+        let is_range_continuation = request.extensions().get::<RangeContinuation>().is_some();
+
+        let response = next.handle(request)?;
+        if !is_range_continuation {
+            (self.0)(&response)?;
+        }
+        Ok(response)
+    }
 }
 
 /// Continuation of a [`Middleware`] chain.
@@ -240,3 +294,54 @@ impl fmt::Debug for MiddlewareChain {
             .finish()
     }
 }
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::test::init_test_log;
+    use crate::transport::set_handler;
+    use crate::{Agent, AgentConfig};
+
+    use super::*;
+
+    #[test]
+    fn after_headers_sees_final_response() {
+        init_test_log();
+        set_handler(
+            "/hook_redirect",
+            302,
+            &[("location", "https://other.test/hook_target")],
+            &[],
+        );
+        set_handler(
+            "/hook_target",
+            200,
+            &[("content-type", "text/html"), ("content-length", "2")],
+            b"hi",
+        );
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls2 = calls.clone();
+
+        let mut config = AgentConfig::default();
+        config.middleware.after_headers(move |res| {
+            calls2.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(res.status(), 200);
+            if res.body().mime_type() == Some("text/html") {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::Other, "html")));
+            }
+            Ok(())
+        });
+        let agent: Agent = config.into();
+
+        let err = agent
+            .get("https://my.test/hook_redirect")
+            .call()
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,268 @@
+//! Completing partial responses with further range requests.
+
+use http::{header, Version};
+use http::{Extensions, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri};
+
+use crate::{Agent, Body, Error, SendBody};
+
+/// Request extension to complete partial responses.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AutoCompletePartial;
+
+/// Request extension marking the follow-up range requests made to complete a partial
+/// response. The [`after_headers`](crate::middleware::MiddlewareChain::after_headers)
+/// hook skips these.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RangeContinuation;
+
+/// The parts of the original request needed to make the follow-up range requests.
+pub(crate) struct PartialRequest {
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    extensions: Extensions,
+}
+
+impl PartialRequest {
+    /// Only for GET requests that opted in, and that don't ask for a range themselves.
+    pub fn new(request: &Request<()>) -> Option<Self> {
+        let wanted = request.method() == Method::GET
+            && request.extensions().get::<AutoCompletePartial>().is_some()
+            && !request.headers().contains_key(header::RANGE);
+
+        if !wanted {
+            return None;
+        }
+
+        let mut extensions = request.extensions().clone();
+        extensions.remove::<AutoCompletePartial>();
+        extensions.insert(RangeContinuation);
+
+        Some(PartialRequest {
+            uri: request.uri().clone(),
+            version: request.version(),
+            headers: request.headers().clone(),
+            extensions,
+        })
+    }
+
+    pub fn complete(
+        self,
+        agent: &Agent,
+        response: Response<Body>,
+    ) -> Result<Response<Body>, Error> {
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Ok(response);
+        }
+
+        let Some((start, total)) = content_range(response.headers()) else {
+            return Err(Error::PartialContent("bad content-range"));
+        };
+
+        if start != 0 {
+            return Err(Error::PartialContent("partial response not from start"));
+        }
+
+        debug!("Complete partial response of total size: {}", total);
+
+        // Only a strong validator ensures the ranges are of the same resource.
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .filter(|v| !v.as_bytes().starts_with(b"W/"))
+            .cloned();
+
+        let (mut parts, body) = response.into_parts();
+        parts.status = StatusCode::OK;
+        parts.headers.remove(header::CONTENT_RANGE);
+        parts
+            .headers
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(total));
+
+        let agent = agent.clone();
+        let body = body.into_ranges(total, move |offset| {
+            self.next_range(&agent, offset, total, etag.as_ref())
+        });
+
+        Ok(Response::from_parts(parts, body))
+    }
+
+    fn next_range(
+        &self,
+        agent: &Agent,
+        offset: u64,
+        total: u64,
+        etag: Option<&HeaderValue>,
+    ) -> Result<Body, Error> {
+        let mut request = Request::builder()
+            .method(Method::GET)
+            .uri(self.uri.clone())
+            .version(self.version)
+            .body(())?;
+
+        *request.extensions_mut() = self.extensions.clone();
+
+        let headers = request.headers_mut();
+        *headers = self.headers.clone();
+
+        // unwrap is ok because the value is only ascii
+        let range = HeaderValue::from_str(&format!("bytes={}-", offset)).unwrap();
+        headers.insert(header::RANGE, range);
+
+        if let Some(etag) = etag {
+            headers.insert(header::IF_RANGE, etag.clone());
+        }
+
+        // Like any other request, the range requests go through the middleware.
+        let response = agent.run_middleware(request, SendBody::none())?;
+
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(Error::PartialContent("range request not honored"));
+        }
+
+        match content_range(response.headers()) {
+            Some((start, t)) if start == offset && t == total => {}
+            _ => return Err(Error::PartialContent("ranges not contiguous")),
+        }
+
+        Ok(response.into_body())
+    }
+}
+
+/// Parses `Content-Range: bytes <start>-<end>/<total>` into start and total.
+fn content_range(headers: &HeaderMap) -> Option<(u64, u64)> {
+    let value = headers.get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let value = value.trim().strip_prefix("bytes ")?;
+
+    let (range, total) = value.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    let total: u64 = total.trim().parse().ok()?;
+
+    (start <= end && end < total).then_some((start, total))
+}
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use crate::test::init_test_log;
+
+    use super::*;
+
+    #[test]
+    fn partial_is_completed() {
+        init_test_log();
+        let mut res = crate::get("https://my.test/partial")
+            .auto_complete_partial(true)
+            .call()
+            .unwrap();
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers().get("content-length").unwrap(), "12");
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello world!");
+    }
+
+    #[test]
+    fn partial_ranges_go_through_middleware() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use crate::middleware::MiddlewareNext;
+        use crate::{AgentConfig, SendBody};
+
+        init_test_log();
+
+        let ranges = Arc::new(AtomicUsize::new(0));
+        let counter = ranges.clone();
+
+        let mut config = AgentConfig::default();
+        config
+            .middleware
+            .add(move |req: Request<SendBody>, next: MiddlewareNext| {
+                if req.headers().contains_key(header::RANGE) {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+                next.handle(req)
+            });
+        let agent: Agent = config.into();
+
+        let mut res = agent
+            .get("https://my.test/partial")
+            .auto_complete_partial(true)
+            .call()
+            .unwrap();
+
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello world!");
+        assert_eq!(ranges.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn partial_ranges_skip_after_headers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use crate::AgentConfig;
+
+        init_test_log();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let mut config = AgentConfig::default();
+        config.middleware.after_headers(move |res| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            // The response made whole.
+            assert_eq!(res.status(), 200);
+            Ok(())
+        });
+        let agent: Agent = config.into();
+
+        let mut res = agent
+            .get("https://my.test/partial")
+            .auto_complete_partial(true)
+            .call()
+            .unwrap();
+
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello world!");
+        // Only for the response to the request made, not the range requests.
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn partial_not_completed_without_opt_in() {
+        init_test_log();
+        let mut res = crate::get("https://my.test/partial").call().unwrap();
+
+        assert_eq!(res.status(), 206);
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn partial_ranges_must_be_contiguous() {
+        init_test_log();
+        let mut res = crate::get("https://my.test/partial_broken")
+            .auto_complete_partial(true)
+            .call()
+            .unwrap();
+
+        let err = res.body_mut().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::PartialContent(_)));
+    }
+
+    #[test]
+    fn parse_content_range() {
+        let range = |v: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_RANGE, HeaderValue::from_str(v).unwrap());
+            content_range(&headers)
+        };
+
+        assert_eq!(range("bytes 0-4/12"), Some((0, 12)));
+        assert_eq!(range("bytes 5-11/12"), Some((5, 12)));
+        assert_eq!(range("bytes 0-4/*"), None);
+        assert_eq!(range("bytes 5-4/12"), None);
+        assert_eq!(range("bytes 0-12/12"), None);
+        assert_eq!(range("items 0-4/12"), None);
+    }
+}
@@ -1,6 +1,6 @@
 use core::fmt;
 use std::collections::VecDeque;
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{Arc, Condvar, Mutex, Weak};
 
 use http::uri::{Authority, Scheme};
 use http::Uri;
@@ -14,6 +14,7 @@ use crate::{AgentConfig, Error};
 pub(crate) struct ConnectionPool {
     connector: Box<dyn Connector>,
     pool: Arc<Mutex<Pool>>,
+    limit: Option<Arc<ConcurrencyLimit>>,
 }
 
 impl ConnectionPool {
@@ -21,22 +22,58 @@ impl ConnectionPool {
         ConnectionPool {
             connector: Box::new(connector),
             pool: Arc::new(Mutex::new(Pool::new(config))),
+            limit: config.max_concurrent_per_host.map(ConcurrencyLimit::new),
         }
     }
 
     pub fn connect(&self, details: &ConnectionDetails) -> Result<Connection, Error> {
         let key = PoolKey::new(details.uri, &details.config.proxy);
 
+        let permit = match &self.limit {
+            Some(limit) => Some(limit.acquire(&key, details)?),
+            None => None,
+        };
+
         {
             let mut pool = self.pool.lock().unwrap();
             pool.purge(details.now);
 
-            if let Some(conn) = pool.get(&key) {
+            if let Some(mut conn) = pool.get(&key) {
                 debug!("Use pooled: {:?}", key);
+                conn.permit = permit;
+                conn.reused = true;
                 return Ok(conn);
             }
         }
 
+        self.open(details, key, permit)
+    }
+
+    /// Opens new connections, not taken from the pool, and adds them to the pool.
+    pub fn warmup(&self, details: &ConnectionDetails, count: usize) -> Result<(), Error> {
+        let key = PoolKey::new(details.uri, &details.config.proxy);
+
+        // Open all first, or the next connect would return the one just added.
+        let mut conns = Vec::with_capacity(count);
+        for _ in 0..count {
+            conns.push(self.open(details, key.clone(), None)?);
+        }
+
+        debug!("Warmed up {} connections: {:?}", conns.len(), key);
+
+        for conn in conns {
+            conn.reuse(details.now);
+        }
+
+        Ok(())
+    }
+
+    fn open(
+        &self,
+        details: &ConnectionDetails,
+        key: PoolKey,
+        permit: Option<Permit>,
+    ) -> Result<Connection, Error> {
         let transport = self
             .connector
             .connect(details, None)?
@@ -48,6 +85,8 @@ impl ConnectionPool {
             last_use: details.now,
             pool: Arc::downgrade(&self.pool),
             position_per_host: None,
+            permit,
+            reused: false,
         };
 
         Ok(conn)
@@ -85,6 +124,12 @@ pub(crate) struct Connection {
     /// Once we have that enumeration, we can drop elements from the front where there
     /// position_per_host >= idle_per_host.
     position_per_host: Option<usize>,
+
+    /// Held while the connection is in flight, when `max_concurrent_per_host` is set.
+    permit: Option<Permit>,
+
+    /// The connection was taken from the pool rather than opened for this request.
+    reused: bool,
 }
 
 impl Connection {
@@ -100,6 +145,10 @@ impl Connection {
         self.transport.await_input(timeout)
     }
 
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
     pub fn consume_input(&mut self, amount: usize) {
         self.transport.buffers().consume(amount)
     }
@@ -120,15 +169,23 @@ impl Connection {
         }
         self.last_use = now;
 
+        // Idle connections are not in flight.
+        self.permit = None;
+
         let Some(arc) = self.pool.upgrade() else {
             debug!("Pool gone: {:?}", self.key);
             return;
         };
 
-        debug!("Return to pool: {:?}", self.key);
-
         let mut pool = arc.lock().unwrap();
 
+        if pool.max_idle_connections == 0 {
+            debug!("Pooling disabled, close: {:?}", self.key);
+            return;
+        }
+
+        debug!("Return to pool: {:?}", self.key);
+
         pool.add(self);
         pool.purge(now);
     }
@@ -259,6 +316,88 @@ impl Pool {
     }
 }
 
+/// Caps the number of in-flight connections per pool key.
+struct ConcurrencyLimit {
+    max: usize,
+    in_flight: Mutex<Vec<(PoolKey, usize)>>,
+    freed: Condvar,
+}
+
+impl ConcurrencyLimit {
+    fn new(max: usize) -> Arc<Self> {
+        Arc::new(ConcurrencyLimit {
+            // A limit of 0 would block forever. Documented on the config field.
+            max: max.max(1),
+            in_flight: Mutex::new(Vec::new()),
+            freed: Condvar::new(),
+        })
+    }
+
+    fn acquire(
+        self: &Arc<Self>,
+        key: &PoolKey,
+        details: &ConnectionDetails,
+    ) -> Result<Permit, Error> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        loop {
+            let count = in_flight
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, c)| *c)
+                .unwrap_or(0);
+
+            if count < self.max {
+                break;
+            }
+
+            debug!("Wait for in-flight slot: {:?}", key);
+
+            let timeout = details.remaining_timeout()?;
+
+            in_flight = if timeout.after.is_not_happening() {
+                self.freed.wait(in_flight).unwrap()
+            } else {
+                self.freed
+                    .wait_timeout(in_flight, *timeout.after)
+                    .unwrap()
+                    .0
+            };
+        }
+
+        match in_flight.iter_mut().find(|(k, _)| k == key) {
+            Some((_, c)) => *c += 1,
+            None => in_flight.push((key.clone(), 1)),
+        }
+
+        Ok(Permit {
+            limit: self.clone(),
+            key: key.clone(),
+        })
+    }
+}
+
+/// A slot in the [`ConcurrencyLimit`], released on drop.
+struct Permit {
+    limit: Arc<ConcurrencyLimit>,
+    key: PoolKey,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut in_flight = self.limit.in_flight.lock().unwrap();
+
+        if let Some(i) = in_flight.iter().position(|(k, _)| *k == self.key) {
+            in_flight[i].1 -= 1;
+            if in_flight[i].1 == 0 {
+                in_flight.swap_remove(i);
+            }
+        }
+
+        self.limit.freed.notify_all();
+    }
+}
+
 impl fmt::Debug for ConnectionPool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ConnectionPool")
@@ -295,4 +434,186 @@ mod test {
         // Test that PoolKey::new() does not panic on unrecognized schemes.
         PoolKey::new(&Uri::from_static("zzz://example.com"), &None);
     }
+
+    #[test]
+    fn max_concurrent_per_host() {
+        use std::time::Duration;
+
+        use crate::{Agent, AgentConfig, TimeoutReason, Timeouts};
+
+        crate::test::init_test_log();
+
+        let agent: Agent = AgentConfig {
+            max_concurrent_per_host: Some(1),
+            timeouts: Timeouts {
+                connect: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        let res = agent.get("https://my.test/get").call().unwrap();
+
+        // The body of the first response is not read, so it's still in flight.
+        let err = agent.get("https://my.test/get").call().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::OpenConnection)));
+
+        // Another host is not affected.
+        agent.get("https://other.test/get").call().unwrap();
+
+        drop(res);
+        agent.get("https://my.test/get").call().unwrap();
+    }
+
+    #[test]
+    fn max_concurrent_per_host_zero_is_one() {
+        use std::time::Duration;
+
+        use crate::{Agent, AgentConfig, TimeoutReason, Timeouts};
+
+        crate::test::init_test_log();
+
+        let agent: Agent = AgentConfig {
+            max_concurrent_per_host: Some(0),
+            timeouts: Timeouts {
+                connect: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        let _res = agent.get("https://my.test/get").call().unwrap();
+
+        let err = agent.get("https://my.test/get").call().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::OpenConnection)));
+    }
+
+    #[test]
+    fn warmup_connections_are_used() {
+        use crate::transport::connection_count;
+        use crate::Agent;
+
+        crate::test::init_test_log();
+
+        let agent = Agent::new_with_defaults();
+
+        // The test server answers by the uri at connect, hence the path.
+        agent.warmup("https://my.test/get", 2).unwrap();
+        assert_eq!(agent.pool_count(), 2);
+
+        let before = connection_count();
+
+        for _ in 0..2 {
+            agent.get("https://my.test/get").call().unwrap();
+        }
+
+        assert_eq!(connection_count(), before);
+    }
+
+    #[test]
+    fn warmup_resolves_connect_proxy() {
+        use std::sync::Mutex;
+
+        use crate::resolver::{ResolvedSocketAddrs, Resolver};
+        use crate::transport::time::NextTimeout;
+        use crate::transport::DefaultConnector;
+        use crate::{Agent, AgentConfig, Proxy};
+
+        crate::test::init_test_log();
+
+        // Records the uris to resolve, without resolving them.
+        #[derive(Debug)]
+        struct Recording(Arc<Mutex<Vec<String>>>);
+
+        impl Resolver for Recording {
+            fn resolve(
+                &self,
+                uri: &Uri,
+                _: &AgentConfig,
+                _: NextTimeout,
+            ) -> Result<ResolvedSocketAddrs, Error> {
+                self.0.lock().unwrap().push(uri.to_string());
+                Err(Error::HostNotFound)
+            }
+        }
+
+        let resolved = Arc::new(Mutex::new(vec![]));
+
+        let config = AgentConfig {
+            proxy: Some(Proxy::new("http://proxy.test:3128").unwrap()),
+            ..Default::default()
+        };
+        let resolver = Recording(resolved.clone());
+        let agent = Agent::with_parts(config, DefaultConnector::new(), resolver);
+
+        let err = agent.warmup("https://my.test/get", 1).unwrap_err();
+
+        assert!(matches!(err, Error::HostNotFound));
+        assert_eq!(*resolved.lock().unwrap(), ["http://proxy.test:3128/"]);
+    }
+
+    #[test]
+    fn response_tells_connection_reused() {
+        use crate::{Agent, ResponseExt};
+
+        crate::test::init_test_log();
+
+        let agent = Agent::new_with_defaults();
+
+        let res = agent.get("https://my.test/get").call().unwrap();
+        assert!(!res.connection_reused());
+
+        agent.warmup("https://my.test/get", 1).unwrap();
+        let res = agent.get("https://my.test/get").call().unwrap();
+        assert!(res.connection_reused());
+    }
+
+    #[test]
+    fn closed_connection_is_not_reused() {
+        use crate::transport::{connection_count, set_handler_version};
+        use crate::Agent;
+
+        crate::test::init_test_log();
+        set_handler_version(
+            "/http10",
+            "HTTP/1.0",
+            200,
+            &[("content-length", "2")],
+            b"ok",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let before = connection_count();
+
+        for _ in 0..2 {
+            let mut res = agent.get("https://my.test/http10").call().unwrap();
+            res.body_mut().read_to_string().unwrap();
+        }
+
+        assert_eq!(connection_count() - before, 2);
+    }
+
+    #[test]
+    fn no_idle_connections_disables_pool() {
+        use crate::transport::connection_count;
+        use crate::{Agent, AgentConfig};
+
+        crate::test::init_test_log();
+        let agent: Agent = AgentConfig {
+            max_idle_connections: 0,
+            ..Default::default()
+        }
+        .into();
+        let before = connection_count();
+
+        for _ in 0..2 {
+            let mut res = agent.get("https://my.test/get").call().unwrap();
+            res.body_mut().read_to_string().unwrap();
+            assert_eq!(agent.pool_count(), 0);
+        }
+
+        assert_eq!(connection_count() - before, 2);
+    }
 }
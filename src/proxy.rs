@@ -2,8 +2,8 @@ use base64::prelude::BASE64_STANDARD;
 use base64::Engine;
 use hoot::parser::try_parse_response;
 use std::convert::{TryFrom, TryInto};
-use std::fmt;
 use std::io::Write;
+use std::{fmt, io};
 
 use http::{StatusCode, Uri};
 
@@ -54,7 +54,7 @@ impl Proxy {
     /// # Arguments:
     ///
     /// * `proxy` - a str of format `<protocol>://<user>:<password>@<host>:port` . All parts
-    ///    except host are optional.
+    ///   except host are optional.
     ///
     /// ###  Protocols
     ///
@@ -64,6 +64,11 @@ impl Proxy {
     /// * `socks4a`: SOCKS4A (requires **socks-proxy** feature)
     /// * `socks5` and `socks`: SOCKS5 (requires **socks-proxy** feature)
     ///
+    /// HTTP proxies are always used via `CONNECT`, also for `http://` targets. The
+    /// request line is written by hoot in origin-form (`GET /path HTTP/1.1`), which
+    /// means absolute-form requests (`GET http://host/path HTTP/1.1`) to a plain
+    /// forwarding proxy are not supported.
+    ///
     /// # Examples proxy formats
     ///
     /// * `http://127.0.0.1:8080`
@@ -191,6 +196,7 @@ impl Connector for ConnectProxyConnector {
             let proxy = details.config.proxy.as_ref().unwrap();
 
             let mut w = TransportAdapter::new(transport);
+            w.timeout = details.remaining_timeout()?;
 
             let uri = &details.uri;
             uri.ensure_valid_url()?;
@@ -221,7 +227,7 @@ impl Connector for ConnectProxyConnector {
             let mut transport = w.into_inner();
 
             let response = loop {
-                let made_progress = transport.await_input(details.timeout)?;
+                let made_progress = transport.await_input(details.remaining_timeout()?)?;
                 let buffers = transport.buffers();
                 let input = buffers.input();
                 let Some((used_input, response)) = try_parse_response::<20>(input)? else {
@@ -252,6 +258,40 @@ impl Connector for ConnectProxyConnector {
     }
 }
 
+/// Failure to reach the proxy itself, as opposed to the proxy failing the request.
+///
+/// [`TcpConnector`](crate::transport::TcpConnector) wraps its errors in this when connecting
+/// to a CONNECT proxy with [`AgentConfig::proxy_fallback_direct`](crate::AgentConfig::proxy_fallback_direct).
+/// The agent takes it as the cue to connect directly, so it never surfaces to the user.
+#[derive(Debug)]
+pub(crate) struct ProxyUnreachable(Error);
+
+impl ProxyUnreachable {
+    pub fn wrap(e: Error) -> Error {
+        Error::Io(io::Error::new(io::ErrorKind::Other, ProxyUnreachable(e)))
+    }
+
+    /// Gives back the original error if the proxy was unreachable, otherwise `Err(e)`.
+    pub fn unwrap(e: Error) -> Result<Error, Error> {
+        match e {
+            Error::Io(e) if e.get_ref().map(|v| v.is::<Self>()).unwrap_or(false) => {
+                // unwrap is ok because we checked the type above.
+                let inner = e.into_inner().unwrap().downcast::<Self>().unwrap();
+                Ok(inner.0)
+            }
+            e => Err(e),
+        }
+    }
+}
+
+impl fmt::Display for ProxyUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proxy unreachable: {}", self.0)
+    }
+}
+
+impl std::error::Error for ProxyUnreachable {}
+
 impl TryFrom<&str> for Proto {
     type Error = Error;
 
@@ -3,13 +3,17 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
-use http::{HeaderName, HeaderValue, Method, Request, Response, Uri, Version};
+use http::{header, HeaderName, HeaderValue, Method, Request, Response, Uri, Version};
 
 use crate::body::Body;
-use crate::send_body::AsSendBody;
+use crate::partial::AutoCompletePartial;
+use crate::send_body::{AsSendBody, BodySent};
 use crate::util::private::Private;
+use crate::util::{form_urlencode, random_key};
 use crate::{Agent, Error, SendBody, Timeouts};
 
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
 /// Transparent wrapper around [`http::request::Builder`].
 ///
 /// The purpose is to provide the [`.call()`][RequestBuilder::call] and [`.send()`][RequestBuilder::send]
@@ -34,6 +38,12 @@ impl<Any> RequestBuilder<Any> {
     /// This function will append the provided key/value as a header to the
     /// set of headers. It does not replace headers.
     ///
+    /// Headers are sent in the order they are first appended. Appending a header
+    /// name again adds the value next to the earlier ones of the same name. Headers
+    /// that ureq adds automatically, such as `host`, `user-agent` and `content-length`,
+    /// are sent before the ones set here. A `host` header set here replaces the one from
+    /// the URI, and if set several times only the last value is sent.
+    ///
     /// # Examples
     ///
     /// ```
@@ -51,6 +61,64 @@ impl<Any> RequestBuilder<Any> {
         self
     }
 
+    /// Set the `Idempotency-Key` header.
+    ///
+    /// APIs use this key to recognize a request sent more than once, such as when
+    /// a client retries after a failure, and only act on it once. To be useful the
+    /// same key must be used for every attempt of the same operation.
+    ///
+    /// Replaces any earlier idempotency key.
+    ///
+    /// ```
+    /// let req = ureq::post("https://httpbin.org/post")
+    ///     .idempotency_key("order-4711");
+    /// ```
+    pub fn idempotency_key<V>(mut self, key: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        if let Some(headers) = self.builder.headers_mut() {
+            headers.remove(IDEMPOTENCY_KEY);
+        }
+
+        self.builder = self.builder.header(IDEMPOTENCY_KEY, key);
+        self
+    }
+
+    /// Set the `Idempotency-Key` header to a new random key.
+    ///
+    /// The key is in the UUID v4 format. Keep the request builder, or the key, around
+    /// if the operation might be retried. See [`RequestBuilder::idempotency_key()`].
+    ///
+    /// ```
+    /// let req = ureq::post("https://httpbin.org/post")
+    ///     .random_idempotency_key();
+    /// ```
+    pub fn random_idempotency_key(self) -> Self {
+        self.idempotency_key(random_key())
+    }
+
+    /// Set whether the connection should be kept alive for reuse after this request.
+    ///
+    /// `false` sends `Connection: close`, and the connection is closed after the response
+    /// rather than returned to the pool. `true` sends `Connection: keep-alive`. Either way,
+    /// the connection is also closed if the server responds with `Connection: close`.
+    ///
+    /// HTTP/1.0 requests always close the connection.
+    ///
+    /// ```
+    /// let req = ureq::get("https://httpbin.org/get")
+    ///     .keep_alive(false);
+    /// ```
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        if let Some(headers) = self.builder.headers_mut() {
+            let value = if enabled { "keep-alive" } else { "close" };
+            headers.insert(header::CONNECTION, HeaderValue::from_static(value));
+        }
+        self
+    }
+
     /// Overrides the URI for this request.
     ///
     /// Typically this is set via `ureq::get(<uri>)` or `Agent::get(<uri>)`. This
@@ -91,7 +159,8 @@ impl<Any> RequestBuilder<Any> {
 
     /// Override agent timeouts on the request level.
     ///
-    /// The agent setting is copied and modified on request level.
+    /// The agent setting is copied and modified on request level. Setting a field to
+    /// `None` disables that timeout for the request, also when the agent has one.
     ///
     /// # Example
     ///
@@ -134,6 +203,31 @@ impl<Any> RequestBuilder<Any> {
         // unwrap is ok because of above logic
         exts.get_mut().unwrap()
     }
+
+    /// Replace the agent timeouts on the request level.
+    ///
+    /// Unlike [`RequestBuilder::timeouts()`], nothing is copied from the agent. Every
+    /// field left as `None` is unlimited for this request.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::Timeouts;
+    /// use std::time::Duration;
+    ///
+    /// let response = ureq::get("https://httpbin.org/get")
+    ///     .with_timeouts(Timeouts {
+    ///         connect: Some(Duration::from_secs(2)),
+    ///         recv_response: Some(Duration::from_secs(10)),
+    ///         ..Default::default()
+    ///     })
+    ///     .call()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        *self.timeouts() = timeouts;
+        self
+    }
 }
 
 impl RequestBuilder<WithoutBody> {
@@ -149,6 +243,90 @@ impl RequestBuilder<WithoutBody> {
         }
     }
 
+    /// Request byte ranges of the resource, with the `Range` header.
+    ///
+    /// Each range is a start offset and an inclusive end offset, or `None` for the
+    /// rest of the resource. A range with the end before the start is not valid and
+    /// is left out. Replaces any earlier `Range` header, and no (valid) ranges removes it.
+    ///
+    /// A server supporting ranges responds `206 Partial Content`, for several ranges
+    /// typically with a `multipart/byteranges` body. The server might coalesce or
+    /// reorder the ranges, or ignore them and send the whole resource with `200 OK`.
+    ///
+    /// ureq doesn't parse `multipart/byteranges`. The body is read as is, and splitting
+    /// it into the parts, using the boundary in the `Content-Type`, is up to the caller.
+    ///
+    /// ```
+    /// let req = ureq::get("http://httpbin.org/range/1024")
+    ///     // Range: bytes=0-99,200-299,1000-
+    ///     .ranges(&[(0, Some(99)), (200, Some(299)), (1000, None)]);
+    /// ```
+    pub fn ranges(mut self, ranges: &[(u64, Option<u64>)]) -> Self {
+        let Some(headers) = self.builder.headers_mut() else {
+            return self;
+        };
+
+        headers.remove(header::RANGE);
+
+        let valid = |(start, end): &&(u64, Option<u64>)| {
+            let ok = end.map(|end| end >= *start).unwrap_or(true);
+            if !ok {
+                debug!("Leave out invalid range: {}-{:?}", start, end);
+            }
+            ok
+        };
+
+        let ranges: Vec<_> = ranges.iter().filter(valid).collect();
+
+        if ranges.is_empty() {
+            return self;
+        }
+
+        let value = ranges
+            .iter()
+            .map(|(start, end)| match end {
+                Some(end) => format!("{}-{}", start, end),
+                None => format!("{}-", start),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // unwrap is ok because the value is only digits and separators.
+        let value = HeaderValue::from_str(&format!("bytes={}", value)).unwrap();
+        headers.insert(header::RANGE, value);
+
+        self
+    }
+
+    /// Complete partial responses by requesting the remaining ranges.
+    ///
+    /// Some servers and proxies answer a request for a full resource with only a part
+    /// of it (`206 Partial Content`). With this enabled, ureq requests the remaining ranges
+    /// as the body is read, and presents them as one continuous body of the full size
+    /// given by `Content-Range`. The response status is changed to `200 OK`.
+    ///
+    /// Only for requests without a `Range` header. Reading the body errors with
+    /// [`Error::PartialContent`] if a part doesn't continue where the previous ended.
+    ///
+    /// The range requests are made by the same agent and go through its middleware.
+    ///
+    /// ```
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .auto_complete_partial(true)
+    ///     .call()?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn auto_complete_partial(mut self, enabled: bool) -> Self {
+        if let Some(exts) = self.builder.extensions_mut() {
+            if enabled {
+                exts.insert(AutoCompletePartial);
+            } else {
+                exts.remove::<AutoCompletePartial>();
+            }
+        }
+        self
+    }
+
     /// Sends the request and blocks the caller until we receive a response.
     ///
     /// It sends neither `Content-Length` nor `Transfer-Encoding`.
@@ -162,6 +340,49 @@ impl RequestBuilder<WithoutBody> {
         let request = self.builder.body(())?;
         do_call(self.agent, request, SendBody::none())
     }
+
+    /// Turns this request into a template, that can be called many times.
+    ///
+    /// Useful for polling, where the same request is sent over and over again.
+    /// Errors if the request is not valid, such as for a bad header value.
+    ///
+    /// ```
+    /// let template = ureq::get("http://httpbin.org/get")
+    ///     .header("accept", "application/json")
+    ///     .template()?;
+    ///
+    /// for _ in 0..2 {
+    ///     let res = template.call()?;
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn template(self) -> Result<RequestTemplate, Error> {
+        Ok(RequestTemplate {
+            agent: self.agent,
+            request: self.builder.body(())?,
+        })
+    }
+}
+
+/// A request without body, that can be called many times.
+///
+/// Created by [`RequestBuilder::template()`].
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    agent: Agent,
+    request: Request<()>,
+}
+
+impl RequestTemplate {
+    /// The request made by each call.
+    pub fn request(&self) -> &Request<()> {
+        &self.request
+    }
+
+    /// Sends a copy of the request and blocks the caller until we receive a response.
+    pub fn call(&self) -> Result<Response<Body>, Error> {
+        do_call(self.agent.clone(), self.request.clone(), SendBody::none())
+    }
 }
 
 impl RequestBuilder<WithBody> {
@@ -196,6 +417,9 @@ impl RequestBuilder<WithBody> {
 
     /// Send body data and blocks the caller until we receive response.
     ///
+    /// The entire body is sent before the response is read. Interleaving writing the
+    /// request body with reading the response (full-duplex) is not supported.
+    ///
     /// ```
     /// let res = ureq::post("http://httpbin.org/post")
     ///     .send(&[0_u8; 1000])?;
@@ -207,6 +431,116 @@ impl RequestBuilder<WithBody> {
         do_call(self.agent, request, data_ref.as_body())
     }
 
+    /// Send body data with a content-type header.
+    ///
+    /// Same as [`RequestBuilder::content_type()`] followed by [`RequestBuilder::send()`],
+    /// except that any content-type set earlier is replaced rather than added to.
+    ///
+    /// ```
+    /// let res = ureq::post("http://httpbin.org/post")
+    ///     .send_with_type("application/octet-stream", &[0_u8; 1000])?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn send_with_type<V>(
+        mut self,
+        content_type: V,
+        data: impl AsSendBody,
+    ) -> Result<Response<Body>, Error>
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        let value = HeaderValue::try_from(content_type).map_err(Into::<http::Error>::into)?;
+
+        // None if the builder already has an error, which send() reports.
+        if let Some(headers) = self.builder.headers_mut() {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+
+        self.send(data)
+    }
+
+    /// Send body data and report the number of body bytes sent.
+    ///
+    /// Same as [`RequestBuilder::send()`], but also returns how many bytes of the
+    /// request body were written to the connection. The count excludes the request
+    /// header and any chunked transfer framing.
+    ///
+    /// ```
+    /// let (res, sent) = ureq::post("http://httpbin.org/post")
+    ///     .send_counted(&[0_u8; 1000])?;
+    ///
+    /// assert_eq!(sent, 1000);
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn send_counted(self, data: impl AsSendBody) -> Result<(Response<Body>, u64), Error> {
+        let mut response = self.send(data)?;
+        let sent = response
+            .extensions_mut()
+            .remove::<BodySent>()
+            .map(|v| v.0)
+            .unwrap_or(0);
+        Ok((response, sent))
+    }
+
+    /// Send body data gzip compressed, with a known length.
+    ///
+    /// Requires the **gzip** feature.
+    ///
+    /// The body is compressed into memory before sending, so the request has a
+    /// `content-length` rather than chunked transfer encoding, which some servers
+    /// reject. The `content-encoding: gzip` header is set.
+    ///
+    /// * Compressed body is limited to 10MB, above that errors with
+    ///   [`Error::BodyExceedsLimit`].
+    ///
+    /// ```
+    /// let res = ureq::post("http://httpbin.org/post")
+    ///     .send_gzip("a body that compresses well, well, well")?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn send_gzip(mut self, data: impl AsSendBody) -> Result<Response<Body>, Error> {
+        let mut data_ref = data;
+        let compressed = gzip_body(data_ref.as_body(), MAX_GZIP_BODY_SIZE)?;
+
+        // None if the builder already has an error, which send() reports.
+        if let Some(headers) = self.builder.headers_mut() {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+
+        self.send(compressed)
+    }
+
+    /// Send form data, `application/x-www-form-urlencoded` encoded.
+    ///
+    /// The pairs are sent in the order given. Repeated names, as sent by HTML forms
+    /// for checkboxes and multi-selects, are kept as is.
+    ///
+    /// ```
+    /// let res = ureq::post("http://httpbin.org/post")
+    ///     .send_form([("fruit", "apple"), ("fruit", "pear"), ("note", "a & b")])?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn send_form<I, K, V>(self, pairs: I) -> Result<Response<Body>, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut form = String::new();
+        for (k, v) in pairs {
+            if !form.is_empty() {
+                form.push('&');
+            }
+            form_urlencode(k.as_ref(), &mut form);
+            form.push('=');
+            form_urlencode(v.as_ref(), &mut form);
+        }
+
+        self.send_with_type("application/x-www-form-urlencoded", form)
+    }
+
     /// Send body data as JSON.
     ///
     /// Requires the **json** feature.
@@ -238,6 +572,37 @@ impl RequestBuilder<WithBody> {
     }
 }
 
+#[cfg(feature = "gzip")]
+const MAX_GZIP_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
+#[cfg(feature = "gzip")]
+fn gzip_body(mut body: SendBody, limit: u64) -> Result<Vec<u8>, Error> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    let mut buf = [0; 8192];
+
+    loop {
+        let n = body.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        enc.write_all(&buf[..n])?;
+        if enc.get_ref().len() as u64 > limit {
+            return Err(Error::BodyExceedsLimit(limit));
+        }
+    }
+
+    let compressed = enc.finish()?;
+    if compressed.len() as u64 > limit {
+        return Err(Error::BodyExceedsLimit(limit));
+    }
+
+    Ok(compressed)
+}
+
 fn do_call(agent: Agent, request: Request<()>, body: SendBody) -> Result<Response<Body>, Error> {
     let response = agent.run_middleware(request, body)?;
     Ok(response)
@@ -298,6 +663,146 @@ mod test {
         );
     }
 
+    #[test]
+    fn with_timeouts_replaces_agent_timeouts() {
+        use std::time::Duration;
+
+        use crate::transport::{set_handler_delivery, Delivery};
+        use crate::{AgentConfig, TimeoutReason};
+
+        crate::test::init_test_log();
+        set_handler_delivery(
+            "/timeouts_slow",
+            200,
+            &[("content-length", "5")],
+            b"hello",
+            Delivery::Slow(Duration::from_millis(20)),
+        );
+
+        let agent: Agent = AgentConfig {
+            timeouts: Timeouts {
+                recv_body: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        let mut res = agent.get("https://my.test/timeouts_slow").call().unwrap();
+        let err = res.body_mut().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::RecvBody)));
+
+        // Only a connect timeout, the agent recv_body timeout no longer applies.
+        let mut res = agent
+            .get("https://my.test/timeouts_slow")
+            .with_timeouts(Timeouts {
+                connect: Some(Duration::from_secs(1)),
+                ..Default::default()
+            })
+            .call()
+            .unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn global_timeout_precedence() {
+        use std::time::Duration;
+
+        use crate::transport::{set_handler_delivery, Delivery};
+        use crate::{AgentConfig, TimeoutReason};
+
+        crate::test::init_test_log();
+        set_handler_delivery(
+            "/global_slow",
+            200,
+            &[("content-length", "5"), ("connection", "close")],
+            b"hello",
+            Delivery::Slow(Duration::from_millis(20)),
+        );
+
+        let agent: Agent = AgentConfig {
+            timeouts: Timeouts {
+                global: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .into();
+
+        // The agent default applies.
+        let mut res = agent.get("https://my.test/global_slow").call().unwrap();
+        let err = res.body_mut().read_to_string().unwrap_err();
+        assert!(matches!(err, Error::Timeout(TimeoutReason::Global)));
+
+        // The request overrides it.
+        let mut req = agent.get("https://my.test/global_slow");
+        req.timeouts().global = Some(Duration::from_secs(5));
+        let mut res = req.call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+
+        // The request disables it.
+        let mut req = agent.get("https://my.test/global_slow");
+        req.timeouts().global = None;
+        let mut res = req.call().unwrap();
+        assert_eq!(res.body_mut().read_to_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn ranges_sets_range_header() {
+        let mut res = crate::get("https://my.test/echo_headers")
+            .header("range", "bytes=5-")
+            .ranges(&[(0, Some(99)), (200, Some(299)), (1000, None)])
+            .call()
+            .unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let ranges: Vec<_> = body.lines().filter(|l| l.starts_with("range:")).collect();
+        assert_eq!(ranges, ["range: bytes=0-99,200-299,1000-"]);
+    }
+
+    #[test]
+    fn ranges_leaves_out_invalid() {
+        let req = crate::get("https://my.test/echo_headers")
+            .ranges(&[(100, Some(99)), (5, Some(5)), (10, None)])
+            .template()
+            .unwrap();
+        assert_eq!(req.request().headers()["range"], "bytes=5-5,10-");
+
+        let req = crate::get("https://my.test/echo_headers")
+            .header("range", "bytes=5-")
+            .ranges(&[(100, Some(99))])
+            .template()
+            .unwrap();
+        assert!(req.request().headers().get("range").is_none());
+    }
+
+    #[test]
+    fn template_is_called_repeatedly() {
+        crate::transport::set_handler(
+            "/template",
+            200,
+            &[("content-length", "2"), ("connection", "close")],
+            b"ok",
+        );
+
+        let template = crate::get("https://my.test/template")
+            .header("x-poll", "1")
+            .template()
+            .unwrap();
+
+        for _ in 0..2 {
+            let mut res = template.call().unwrap();
+            assert_eq!(res.body_mut().read_to_string().unwrap(), "ok");
+        }
+        assert_eq!(template.request().headers()["x-poll"], "1");
+
+        let err = crate::get("https://my.test/template")
+            .header("x-bad", "\n")
+            .template()
+            .unwrap_err();
+        assert!(matches!(err, Error::Http(_)));
+    }
+
     #[test]
     fn debug_print_with_body() {
         let call = crate::post("https://foo/bar");
@@ -306,4 +811,251 @@ mod test {
             "RequestBuilder<WithBody> { method: POST, uri: https://foo/bar }"
         );
     }
+
+    #[test]
+    fn send_counted_reports_body_bytes() {
+        let (_, sent) = crate::post("http://httpbin.org/post")
+            .send_counted("hello world")
+            .unwrap();
+        assert_eq!(sent, 11);
+    }
+
+    #[test]
+    fn send_with_type_replaces_content_type() {
+        let mut res = crate::post("https://my.test/echo_headers")
+            .content_type("text/plain")
+            .send_with_type("application/octet-stream", &[1_u8, 2, 3][..])
+            .unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let types: Vec<_> = body
+            .lines()
+            .filter(|l| l.starts_with("content-type:"))
+            .collect();
+        assert_eq!(types, ["content-type: application/octet-stream"]);
+    }
+
+    #[test]
+    fn idempotency_key_replaces_earlier() {
+        let mut res = crate::post("https://my.test/echo_headers")
+            .random_idempotency_key()
+            .idempotency_key("order-4711")
+            .send("")
+            .unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let keys: Vec<_> = body
+            .lines()
+            .filter(|l| l.starts_with("idempotency-key:"))
+            .collect();
+        assert_eq!(keys, ["idempotency-key: order-4711"]);
+    }
+
+    #[test]
+    fn random_keys_are_unique() {
+        let a = random_key();
+        let b = random_key();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 36);
+        assert_eq!(a.as_bytes()[14], b'4');
+    }
+
+    #[test]
+    fn headers_sent_in_insertion_order() {
+        use crate::transport::{last_request, record_requests};
+
+        record_requests();
+
+        crate::get("https://my.test/echo_headers")
+            .header("x-b", "1")
+            .header("x-a", "2")
+            .header("x-c", "3")
+            .header("x-b", "4")
+            .call()
+            .unwrap();
+
+        // The raw request, since the parsed headers group values by name.
+        let head = String::from_utf8(last_request().unwrap().head).unwrap();
+        let names: Vec<_> = head
+            .lines()
+            .skip(1)
+            .filter_map(|l| l.split_once(':'))
+            .map(|(n, _)| n.to_ascii_lowercase())
+            .collect();
+        let host = names.iter().position(|n| n == "host").unwrap();
+        // A repeated name is sent next to its first occurrence.
+        assert_eq!(names[host + 1..], ["x-b", "x-b", "x-a", "x-c"]);
+    }
+
+    #[test]
+    fn single_host_header_is_sent() {
+        let mut res = crate::get("https://my.test/echo_headers")
+            .header("host", "first.test")
+            .header("host", "second.test")
+            .call()
+            .unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let hosts: Vec<_> = body.lines().filter(|l| l.starts_with("host:")).collect();
+        assert_eq!(hosts, ["host: second.test"]);
+
+        // Without a manual host header, it's from the uri.
+        let mut res = crate::get("https://my.test/echo_headers").call().unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let hosts: Vec<_> = body.lines().filter(|l| l.starts_with("host:")).collect();
+        assert_eq!(hosts, ["host: my.test"]);
+    }
+
+    #[test]
+    fn wrong_content_length_is_corrected() {
+        let mut res = crate::post("https://my.test/echo_headers")
+            .header("content-length", "100")
+            .send("hello")
+            .unwrap();
+        let body = res.body_mut().read_to_string().unwrap();
+
+        let lengths: Vec<_> = body
+            .lines()
+            .filter(|l| l.starts_with("content-length"))
+            .collect();
+        assert_eq!(lengths, ["content-length: 5"]);
+    }
+
+    #[test]
+    fn connection_close_is_not_pooled() {
+        use crate::transport::{connection_count, set_handler};
+
+        set_handler(
+            "/close_response",
+            200,
+            &[("connection", "close"), ("content-length", "2")],
+            b"ok",
+        );
+
+        let agent = Agent::new_with_defaults();
+        let before = connection_count();
+
+        let mut res = agent
+            .get("https://my.test/get")
+            .keep_alive(false)
+            .call()
+            .unwrap();
+        res.body_mut().read_to_string().unwrap();
+        assert_eq!(agent.pool_count(), 0);
+
+        let mut res = agent.get("https://my.test/close_response").call().unwrap();
+        res.body_mut().read_to_string().unwrap();
+        assert_eq!(agent.pool_count(), 0);
+
+        assert_eq!(connection_count() - before, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn send_gzip_has_content_length() {
+        use std::io::Read;
+
+        use flate2::read::GzDecoder;
+
+        use crate::{AgentConfig, ResponseExt};
+
+        let agent: Agent = AgentConfig {
+            retain_request_body: Some(1024),
+            ..Default::default()
+        }
+        .into();
+
+        let text = "hello hello hello hello world";
+        let mut res = agent
+            .post("https://my.test/echo_headers")
+            .send_gzip(text)
+            .unwrap();
+
+        let sent = res.sent_body().unwrap().to_vec();
+        let mut decoded = String::new();
+        GzDecoder::new(&sent[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+
+        let headers = res.body_mut().read_to_string().unwrap();
+        assert!(headers.contains("content-encoding: gzip\n"));
+        assert!(headers.contains(&format!("content-length: {}\n", sent.len())));
+        assert!(!headers.contains("transfer-encoding"));
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn gzip_body_is_limited() {
+        // Endless bytes that don't compress.
+        struct Rand(u64);
+
+        impl std::io::Read for Rand {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                for b in buf.iter_mut() {
+                    self.0 ^= self.0 << 13;
+                    self.0 ^= self.0 >> 7;
+                    self.0 ^= self.0 << 17;
+                    *b = self.0 as u8;
+                }
+                Ok(buf.len())
+            }
+        }
+
+        let err = gzip_body(SendBody::from_owned_reader(Rand(1)), 1024).unwrap_err();
+        assert!(matches!(err, Error::BodyExceedsLimit(1024)));
+    }
+
+    #[test]
+    fn test_server_records_requests() {
+        use std::io::Cursor;
+
+        use crate::transport::{last_request, record_requests, recorded_requests};
+
+        record_requests();
+        let before = recorded_requests().len();
+
+        crate::post("https://my.test/echo_headers?q=1")
+            .header("x-custom", "yes")
+            .send("hello")
+            .unwrap();
+
+        let req = last_request().unwrap();
+        assert_eq!(req.method, Method::POST);
+        assert_eq!(req.uri, "https://my.test/echo_headers?q=1");
+        assert_eq!(req.headers.get("x-custom").unwrap(), "yes");
+        assert_eq!(req.body, b"hello");
+
+        // A body from a reader is chunked.
+        let mut reader = Cursor::new(b"streamed body");
+        crate::put("https://my.test/echo_headers")
+            .send(SendBody::from_reader(&mut reader))
+            .unwrap();
+
+        let req = last_request().unwrap();
+        assert_eq!(req.method, Method::PUT);
+        assert_eq!(req.headers.get("transfer-encoding").unwrap(), "chunked");
+        assert_eq!(req.body, b"streamed body");
+
+        assert_eq!(recorded_requests().len() - before, 2);
+    }
+
+    #[test]
+    fn send_form_keeps_repeated_names() {
+        use crate::{AgentConfig, ResponseExt};
+
+        let agent: Agent = AgentConfig {
+            retain_request_body: Some(1024),
+            ..Default::default()
+        }
+        .into();
+
+        let res = agent
+            .post("https://my.test/get")
+            .send_form([("a", "1"), ("a", "2"), ("b c", "ö&=+")])
+            .unwrap();
+
+        assert_eq!(res.sent_body(), Some(&b"a=1&a=2&b+c=%C3%B6%26%3D%2B"[..]));
+    }
 }
@@ -0,0 +1,966 @@
+use std::time::{Duration, SystemTime};
+
+use http::{header, HeaderMap, HeaderName, Response, StatusCode, Uri};
+
+use crate::auth::{parse_challenges, AuthChallenge};
+use crate::send_body::SentBody;
+#[cfg(feature = "_url")]
+use crate::util::UriExt;
+use crate::util::{parse_content_type, parse_http_date, HeaderMapExt};
+
+/// Hop-by-hop headers as per RFC 7230 section 6.1. These are only meaningful for a
+/// single connection and must not be forwarded by proxies.
+const HOP_BY_HOP: &[HeaderName] = &[
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Response extension with the time it took to receive the response headers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Elapsed(pub Duration);
+
+/// Response extension marking a response that has no body, such as for a HEAD request,
+/// regardless of the headers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NoBody;
+
+/// Response extension with the uri of the request, after following redirects.
+///
+/// Only read by `content_location`, which needs the url crate.
+#[cfg_attr(not(feature = "_url"), allow(dead_code))]
+#[derive(Debug, Clone)]
+pub(crate) struct RequestUri(pub Uri);
+
+/// Response extension marking a response received on a connection from the pool.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConnectionReused;
+
+/// The class of a response status code, as per RFC 9110 section 15.
+///
+/// See [`ResponseExt::status_category()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusCategory {
+    /// 1xx
+    Informational,
+    /// 2xx
+    Success,
+    /// 3xx
+    Redirection,
+    /// 4xx
+    ClientError,
+    /// 5xx
+    ServerError,
+    /// Any other code, 600 and up.
+    Unknown,
+}
+
+impl From<StatusCode> for StatusCategory {
+    fn from(status: StatusCode) -> Self {
+        if status.is_informational() {
+            StatusCategory::Informational
+        } else if status.is_success() {
+            StatusCategory::Success
+        } else if status.is_redirection() {
+            StatusCategory::Redirection
+        } else if status.is_client_error() {
+            StatusCategory::ClientError
+        } else if status.is_server_error() {
+            StatusCategory::ServerError
+        } else {
+            StatusCategory::Unknown
+        }
+    }
+}
+
+/// Extension trait for [`http::Response`].
+pub trait ResponseExt {
+    /// The headers of the response that are fit to be forwarded.
+    ///
+    /// This strips hop-by-hop headers as per RFC 7230, such as `Connection`,
+    /// `Keep-Alive` and `Transfer-Encoding`, as well as any header named in the
+    /// `Connection` header. Useful for proxy or gateway like uses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::http::Response::builder()
+    ///     .header("content-type", "text/plain")
+    ///     .header("connection", "keep-alive, x-custom")
+    ///     .header("keep-alive", "timeout=5")
+    ///     .header("x-custom", "foo")
+    ///     .body(())?;
+    ///
+    /// let headers = res.forwardable_headers();
+    ///
+    /// assert_eq!(headers.len(), 1);
+    /// assert!(headers.contains_key("content-type"));
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn forwardable_headers(&self) -> HeaderMap;
+
+    /// The request body that was sent for this response.
+    ///
+    /// Requires [`AgentConfig::retain_request_body`](crate::AgentConfig::retain_request_body)
+    /// to be set. `None` for bodies streamed from a reader, or bodies larger than the
+    /// configured size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::{Agent, AgentConfig, ResponseExt};
+    ///
+    /// let agent: Agent = AgentConfig {
+    ///     retain_request_body: Some(1024),
+    ///     ..Default::default()
+    /// }
+    /// .into();
+    ///
+    /// let res = agent.post("http://httpbin.org/post")
+    ///     .send("audit me")?;
+    ///
+    /// assert_eq!(res.sent_body(), Some(&b"audit me"[..]));
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn sent_body(&self) -> Option<&[u8]>;
+
+    /// Time from the start of the call until the response headers were received.
+    ///
+    /// This includes any followed redirects, but not reading the response body.
+    /// `None` for responses not made by ureq.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// println!("Took {:?}", res.elapsed().unwrap());
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn elapsed(&self) -> Option<Duration>;
+
+    /// Whether the request was sent on a connection from the pool.
+    ///
+    /// `false` when a new connection was opened for the request, and for responses not
+    /// made by ureq. With redirects, this is about the connection of the last request.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// println!("Reused connection: {}", res.connection_reused());
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn connection_reused(&self) -> bool;
+
+    /// The authentication challenges of the `WWW-Authenticate` headers.
+    ///
+    /// Typically sent with a `401 Unauthorized` response, to say which authentication
+    /// schemes the server accepts. A header can hold several challenges, and there can be
+    /// several headers. Challenges are in the order sent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::http::Response::builder()
+    ///     .status(401)
+    ///     .header("www-authenticate", r#"Digest realm="api", nonce="7ypf""#)
+    ///     .body(())?;
+    ///
+    /// let challenges = res.auth_challenges();
+    ///
+    /// assert_eq!(challenges[0].scheme(), "Digest");
+    /// assert_eq!(challenges[0].param("realm"), Some("api"));
+    /// assert_eq!(challenges[0].param("nonce"), Some("7ypf"));
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn auth_challenges(&self) -> Vec<AuthChallenge>;
+
+    /// The `Content-Location` header, resolved against the request uri.
+    ///
+    /// The header gives the uri of the returned content, which can differ from the
+    /// requested uri. A relative value is resolved against the uri of the request that
+    /// produced this response, i.e. after following any redirects.
+    ///
+    /// `None` if the header is missing or not valid, or when the response did not come
+    /// from a request made by ureq.
+    ///
+    /// Requires the **cookies** feature, which brings in the url crate used to resolve
+    /// the value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// if let Some(location) = res.content_location() {
+    ///     println!("Content is at {}", location);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    #[cfg(feature = "_url")]
+    fn content_location(&self) -> Option<String>;
+
+    /// The size of the body as given by the `Content-Length` header.
+    ///
+    /// `None` if the header is missing or not a number. Responses that can't have a body,
+    /// i.e. `204 No Content`, `304 Not Modified` and responses to `HEAD` requests,
+    /// are `Some(0)`, whatever the header says.
+    ///
+    /// This is the size before any decompression of the body.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// assert_eq!(res.content_length(), Some(100));
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn content_length(&self) -> Option<u64>;
+
+    /// The content codings from the `Content-Encoding` header, in the order applied.
+    ///
+    /// Values are lowercased and `identity` is left out. The header is as sent by the
+    /// server, also if ureq decompresses the body while reading it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// for coding in res.content_encoding() {
+    ///     println!("Encoded with {}", coding);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn content_encoding(&self) -> Vec<String>;
+
+    /// The request header names listed in the `Vary` header.
+    ///
+    /// These are the headers, besides the URL, that select this response and so belong in a
+    /// cache key. Names are lowercased. `Vary: *` is returned as `["*"]`, and means the
+    /// response can't be reused from a cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// let cacheable = !res.vary().iter().any(|v| v == "*");
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn vary(&self) -> Vec<String>;
+
+    /// The parameters of the `Content-Type` header, such as `charset` or `boundary`.
+    ///
+    /// For the below header, we would get `[("charset", "utf-8"), ("boundary", "a;b")]`:
+    ///
+    /// ```text
+    ///     Content-Type: multipart/form-data; charset=utf-8; boundary="a;b"
+    /// ```
+    ///
+    /// Names are lowercased and quoted values are unquoted. The mime type itself is
+    /// available as [`Body::mime_type()`](crate::Body::mime_type).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// let boundary = res
+    ///     .content_type_params()
+    ///     .into_iter()
+    ///     .find(|(name, _)| name == "boundary");
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn content_type_params(&self) -> Vec<(String, String)>;
+
+    /// The class of the status code, for matching on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::{ResponseExt, StatusCategory};
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// match res.status_category() {
+    ///     StatusCategory::Success => println!("ok"),
+    ///     StatusCategory::Redirection => println!("moved"),
+    ///     other => println!("failed: {:?}", other),
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn status_category(&self) -> StatusCategory;
+
+    /// Whether the server supports byte range requests for this resource.
+    ///
+    /// True if the `Accept-Ranges` header lists `bytes`. False if it is `none`, or
+    /// missing. A server might still honor ranges without the header, but this is
+    /// the signal to rely on before resuming a download.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/bytes/100")
+    ///     .call()?;
+    ///
+    /// if res.accepts_ranges() {
+    ///     println!("Download can be resumed");
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn accepts_ranges(&self) -> bool;
+
+    /// Whether the response may have a body to read.
+    ///
+    /// False for responses that can't have a body, i.e. `204 No Content`,
+    /// `304 Not Modified` and responses to `HEAD` requests, and for `Content-Length: 0`.
+    /// Otherwise true, also for a chunked body, which might turn out to be empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::head("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// assert!(!res.has_body());
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn has_body(&self) -> bool;
+
+    /// The `Last-Modified` header as a time.
+    ///
+    /// `None` if the header is missing or not a valid HTTP date. The obsolete RFC 850 and
+    /// asctime formats are accepted as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// if let Some(time) = res.last_modified() {
+    ///     println!("Modified at {:?}", time);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn last_modified(&self) -> Option<SystemTime>;
+
+    /// The `Date` header as a time.
+    ///
+    /// This is when the server created the response. `None` if the header is missing or
+    /// not a valid HTTP date.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// if let Some(time) = res.date() {
+    ///     println!("Created at {:?}", time);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn date(&self) -> Option<SystemTime>;
+
+    /// The `ETag` header, verbatim.
+    ///
+    /// Includes the quotes, and the `W/` prefix of a weak tag. This is the value to send
+    /// in an `If-None-Match` header to revalidate the response.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// if let Some(etag) = res.etag() {
+    ///     let res = ureq::get("http://httpbin.org/get")
+    ///         .header("if-none-match", etag)
+    ///         .call()?;
+    ///
+    ///     if res.is_not_modified() {
+    ///         println!("Cached copy is still fresh");
+    ///     }
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn etag(&self) -> Option<&str>;
+
+    /// Whether the response is `304 Not Modified`.
+    ///
+    /// The answer to a conditional request, such as with `If-None-Match`, when the
+    /// cached copy is still valid.
+    fn is_not_modified(&self) -> bool;
+
+    /// How long to wait before retrying, from the `Retry-After` header.
+    ///
+    /// Typically sent with `429 Too Many Requests` and `503 Service Unavailable`. The
+    /// header is either a number of seconds, or an HTTP date that is compared with the
+    /// current time. A date in the past gives [`Duration::ZERO`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ureq::ResponseExt;
+    ///
+    /// let res = ureq::get("http://httpbin.org/get")
+    ///     .call()?;
+    ///
+    /// if let Some(wait) = res.retry_after() {
+    ///     std::thread::sleep(wait);
+    /// }
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    fn retry_after(&self) -> Option<Duration>;
+}
+
+impl<B> ResponseExt for Response<B> {
+    fn forwardable_headers(&self) -> HeaderMap {
+        let headers = self.headers();
+
+        // Headers listed in the Connection header are also hop-by-hop.
+        let listed = headers.get_list(header::CONNECTION);
+
+        let is_hop_by_hop = |name: &HeaderName| {
+            HOP_BY_HOP.contains(name)
+                || name.as_str() == "keep-alive"
+                || name.as_str() == "proxy-connection"
+                || listed.iter().any(|l| l.eq_ignore_ascii_case(name.as_str()))
+        };
+
+        let mut forward = HeaderMap::new();
+
+        for (name, value) in headers {
+            if !is_hop_by_hop(name) {
+                forward.append(name.clone(), value.clone());
+            }
+        }
+
+        forward
+    }
+
+    fn sent_body(&self) -> Option<&[u8]> {
+        self.extensions().get::<SentBody>().map(|v| &v.0[..])
+    }
+
+    fn elapsed(&self) -> Option<Duration> {
+        self.extensions().get::<Elapsed>().map(|v| v.0)
+    }
+
+    fn connection_reused(&self) -> bool {
+        self.extensions().get::<ConnectionReused>().is_some()
+    }
+
+    fn auth_challenges(&self) -> Vec<AuthChallenge> {
+        let mut challenges = vec![];
+        for value in self.headers().get_all(header::WWW_AUTHENTICATE) {
+            if let Ok(v) = value.to_str() {
+                parse_challenges(v, &mut challenges);
+            }
+        }
+        challenges
+    }
+
+    fn content_type_params(&self) -> Vec<(String, String)> {
+        self.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| parse_content_type(v).1)
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "_url")]
+    fn content_location(&self) -> Option<String> {
+        let base = self
+            .extensions()
+            .get::<RequestUri>()?
+            .0
+            .try_into_url()
+            .ok()?;
+        let value = self
+            .headers()
+            .get(header::CONTENT_LOCATION)?
+            .to_str()
+            .ok()?;
+        base.join(value.trim()).ok().map(String::from)
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        let status = self.status();
+        let no_body = self.extensions().get::<NoBody>().is_some()
+            || status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED;
+
+        if no_body {
+            return Some(0);
+        }
+
+        self.headers().content_length()
+    }
+
+    fn content_encoding(&self) -> Vec<String> {
+        self.headers()
+            .get_list(header::CONTENT_ENCODING)
+            .into_iter()
+            .map(|v| v.to_ascii_lowercase())
+            .filter(|v| v != "identity")
+            .collect()
+    }
+
+    fn vary(&self) -> Vec<String> {
+        let names: Vec<String> = self
+            .headers()
+            .get_list(header::VARY)
+            .into_iter()
+            .map(|v| v.to_ascii_lowercase())
+            .collect();
+
+        if names.iter().any(|v| v == "*") {
+            return vec!["*".to_string()];
+        }
+
+        names
+    }
+
+    fn status_category(&self) -> StatusCategory {
+        self.status().into()
+    }
+
+    fn has_body(&self) -> bool {
+        self.content_length() != Some(0)
+    }
+
+    fn etag(&self) -> Option<&str> {
+        self.headers().get(header::ETAG)?.to_str().ok()
+    }
+
+    fn is_not_modified(&self) -> bool {
+        self.status() == StatusCode::NOT_MODIFIED
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        let value = self
+            .headers()
+            .get(header::RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim();
+
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+
+        let time = parse_http_date(value)?;
+        Some(
+            time.duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+
+    fn last_modified(&self) -> Option<SystemTime> {
+        let value = self.headers().get(header::LAST_MODIFIED)?.to_str().ok()?;
+        parse_http_date(value)
+    }
+
+    fn date(&self) -> Option<SystemTime> {
+        let value = self.headers().get(header::DATE)?.to_str().ok()?;
+        parse_http_date(value)
+    }
+
+    fn accepts_ranges(&self) -> bool {
+        self.headers()
+            .get_list(header::ACCEPT_RANGES)
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case("bytes"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn forwardable_headers_strips_hop_by_hop() {
+        let res = Response::builder()
+            .header("content-type", "text/plain")
+            .header("transfer-encoding", "chunked")
+            .header("keep-alive", "timeout=5")
+            .header("proxy-connection", "keep-alive")
+            .header("upgrade", "websocket")
+            .header("set-cookie", "a=1")
+            .header("set-cookie", "b=2")
+            .body(())
+            .unwrap();
+
+        let headers = res.forwardable_headers();
+
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get("content-type").unwrap(), "text/plain");
+        assert_eq!(headers.get_all("set-cookie").iter().count(), 2);
+    }
+
+    #[test]
+    fn forwardable_headers_honors_connection_tokens() {
+        let res = Response::builder()
+            .header("connection", "close, X-Foo")
+            .header("connection", "x-bar")
+            .header("x-foo", "1")
+            .header("x-bar", "2")
+            .header("x-baz", "3")
+            .body(())
+            .unwrap();
+
+        let headers = res.forwardable_headers();
+
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("x-baz"));
+    }
+
+    #[test]
+    fn sent_body_is_capped() {
+        use crate::{Agent, AgentConfig, SendBody};
+
+        let agent: Agent = AgentConfig {
+            retain_request_body: Some(5),
+            ..Default::default()
+        }
+        .into();
+
+        let res = agent.post("http://httpbin.org/post").send("hello").unwrap();
+        assert_eq!(res.sent_body(), Some(&b"hello"[..]));
+
+        let res = agent
+            .post("http://httpbin.org/post")
+            .send("too long")
+            .unwrap();
+        assert_eq!(res.sent_body(), None);
+
+        let mut reader: &[u8] = b"hi";
+        let res = agent
+            .post("http://httpbin.org/post")
+            .send(SendBody::from_reader(&mut reader))
+            .unwrap();
+        assert_eq!(res.sent_body(), None);
+    }
+
+    #[test]
+    fn elapsed_until_response_headers() {
+        let res = crate::get("https://my.test/delay").call().unwrap();
+        assert!(res.elapsed().unwrap() >= Duration::from_millis(200));
+
+        let res = Response::new(());
+        assert_eq!(res.elapsed(), None);
+    }
+
+    #[test]
+    fn content_length_of_responses() {
+        let res = crate::get("https://my.test/get").call().unwrap();
+        assert!(res.content_length().unwrap() > 0);
+
+        let res = crate::head("https://my.test/get").call().unwrap();
+        assert!(res.headers().contains_key("content-length"));
+        assert_eq!(res.content_length(), Some(0));
+
+        let length = |status: u16, value: &str| {
+            Response::builder()
+                .status(status)
+                .header("content-length", value)
+                .body(())
+                .unwrap()
+                .content_length()
+        };
+
+        assert_eq!(length(200, "12"), Some(12));
+        assert_eq!(length(200, "twelve"), None);
+        assert_eq!(length(204, "12"), Some(0));
+        assert_eq!(length(304, "12"), Some(0));
+        assert_eq!(Response::new(()).content_length(), None);
+    }
+
+    #[test]
+    fn has_body_of_responses() {
+        let res = crate::get("https://my.test/get").call().unwrap();
+        assert!(res.has_body());
+
+        let res = crate::head("https://my.test/get").call().unwrap();
+        assert!(!res.has_body());
+
+        let has_body = |status: u16, length: Option<&str>| {
+            let mut builder = Response::builder().status(status);
+            if let Some(v) = length {
+                builder = builder.header("content-length", v);
+            }
+            builder.body(()).unwrap().has_body()
+        };
+
+        assert!(!has_body(204, None));
+        assert!(!has_body(304, Some("12")));
+        assert!(!has_body(200, Some("0")));
+        assert!(has_body(200, None));
+    }
+
+    #[test]
+    fn etag_and_not_modified() {
+        let res = Response::builder()
+            .header("etag", "W/\"abc\"")
+            .body(())
+            .unwrap();
+        assert_eq!(res.etag(), Some("W/\"abc\""));
+        assert!(!res.is_not_modified());
+
+        let res = Response::builder().status(304).body(()).unwrap();
+        assert_eq!(res.etag(), None);
+        assert!(res.is_not_modified());
+    }
+
+    #[test]
+    fn http_dates() {
+        // 784111777 is Sun, 06 Nov 1994 08:49:37 GMT
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+
+        let res = Response::builder()
+            .header("last-modified", "Sun, 06 Nov 1994 08:49:37 GMT")
+            .header("date", "Sunday, 06-Nov-94 08:49:37 GMT")
+            .body(())
+            .unwrap();
+        assert_eq!(res.last_modified(), Some(expected));
+        assert_eq!(res.date(), Some(expected));
+
+        let res = Response::builder()
+            .header("last-modified", "Sun Nov  6 08:49:37 1994")
+            .header("date", "Sun, 06 Foo 1994 08:49:37 GMT")
+            .body(())
+            .unwrap();
+        assert_eq!(res.last_modified(), Some(expected));
+        assert_eq!(res.date(), None);
+
+        let res = Response::builder()
+            .header("date", "Thu, 29 Feb 2024 23:59:60 GMT")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            res.date(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_251_200))
+        );
+
+        // Days that don't exist in the month.
+        for date in [
+            "Sat, 31 Feb 2024 00:00:00 GMT",
+            "Thu, 29 Feb 2023 00:00:00 GMT",
+            "Mon, 29 Feb 1900 00:00:00 GMT",
+            "Thu, 31 Apr 2024 00:00:00 GMT",
+        ] {
+            let res = Response::builder().header("date", date).body(()).unwrap();
+            assert_eq!(res.date(), None, "{}", date);
+        }
+
+        // Years that would overflow the calculation, and other out of range values.
+        for date in [
+            "Sun, 06 Nov 9223372036854775807 08:49:37 GMT",
+            "Sun, 06 Nov -9223372036854775808 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 10000",
+            "Sun, 06 Nov 1994 -1:49:37 GMT",
+        ] {
+            let res = Response::builder().header("date", date).body(()).unwrap();
+            assert_eq!(res.date(), None, "{}", date);
+        }
+
+        assert_eq!(Response::new(()).last_modified(), None);
+    }
+
+    #[test]
+    fn retry_after_seconds_and_date() {
+        let res = Response::builder()
+            .header("retry-after", "120")
+            .body(())
+            .unwrap();
+        assert_eq!(res.retry_after(), Some(Duration::from_secs(120)));
+
+        let res = Response::builder()
+            .header("retry-after", "Sun, 06 Nov 1994 08:49:37 GMT")
+            .body(())
+            .unwrap();
+        assert_eq!(res.retry_after(), Some(Duration::ZERO));
+
+        let res = Response::builder()
+            .header("retry-after", "Fri, 31 Dec 9999 23:59:59 GMT")
+            .body(())
+            .unwrap();
+        assert!(res.retry_after().unwrap() > Duration::from_secs(3600));
+
+        let res = Response::builder()
+            .header("retry-after", "soon")
+            .body(())
+            .unwrap();
+        assert_eq!(res.retry_after(), None);
+
+        assert_eq!(Response::new(()).retry_after(), None);
+    }
+
+    #[test]
+    fn content_type_params_parsed() {
+        let res = Response::builder()
+            .header(
+                "content-type",
+                "multipart/form-data ; Charset=utf-8;boundary=\"a;\\\"b\" ; bad",
+            )
+            .body(())
+            .unwrap();
+
+        assert_eq!(
+            res.content_type_params(),
+            vec![
+                ("charset".to_string(), "utf-8".to_string()),
+                ("boundary".to_string(), "a;\"b".to_string()),
+            ]
+        );
+
+        let res = Response::builder()
+            .header("content-type", "text/plain")
+            .body(())
+            .unwrap();
+        assert!(res.content_type_params().is_empty());
+        assert!(Response::new(()).content_type_params().is_empty());
+    }
+
+    #[test]
+    fn content_encoding_in_order() {
+        let res = Response::builder()
+            .header("content-encoding", "gzip, BR")
+            .header("content-encoding", "identity")
+            .body(())
+            .unwrap();
+
+        assert_eq!(res.content_encoding(), vec!["gzip", "br"]);
+
+        let res = Response::builder().body(()).unwrap();
+        assert!(res.content_encoding().is_empty());
+    }
+
+    #[test]
+    fn vary_lists_header_names() {
+        let res = Response::builder()
+            .header("vary", "Accept-Encoding, User-Agent")
+            .body(())
+            .unwrap();
+
+        assert_eq!(res.vary(), vec!["accept-encoding", "user-agent"]);
+
+        let res = Response::builder()
+            .header("vary", "accept")
+            .header("vary", "*")
+            .body(())
+            .unwrap();
+
+        assert_eq!(res.vary(), vec!["*"]);
+    }
+
+    #[test]
+    fn status_category_of_codes() {
+        let cases = [
+            (100, StatusCategory::Informational),
+            (200, StatusCategory::Success),
+            (204, StatusCategory::Success),
+            (301, StatusCategory::Redirection),
+            (404, StatusCategory::ClientError),
+            (503, StatusCategory::ServerError),
+            (699, StatusCategory::Unknown),
+        ];
+
+        for (code, category) in cases.iter() {
+            let res = Response::builder().status(*code).body(()).unwrap();
+            assert_eq!(res.status_category(), *category, "{}", code);
+        }
+    }
+
+    #[test]
+    fn accepts_ranges_header() {
+        let res = Response::builder()
+            .header("accept-ranges", "bytes")
+            .body(())
+            .unwrap();
+        assert!(res.accepts_ranges());
+
+        let res = Response::builder()
+            .header("accept-ranges", "none")
+            .body(())
+            .unwrap();
+        assert!(!res.accepts_ranges());
+
+        let res = Response::builder().body(()).unwrap();
+        assert!(!res.accepts_ranges());
+    }
+
+    #[test]
+    #[cfg(feature = "_url")]
+    fn content_location_is_resolved() {
+        let location = |value: &str| {
+            let mut res = Response::builder()
+                .header("content-location", value)
+                .body(())
+                .unwrap();
+            let uri = "https://my.test/a/b/page?q=1".parse().unwrap();
+            res.extensions_mut().insert(RequestUri(uri));
+            res.content_location()
+        };
+
+        assert_eq!(location("other").unwrap(), "https://my.test/a/b/other");
+        assert_eq!(location("../c/d").unwrap(), "https://my.test/a/c/d");
+        assert_eq!(location("/top/./x").unwrap(), "https://my.test/top/x");
+        assert_eq!(location("?q=2").unwrap(), "https://my.test/a/b/page?q=2");
+        assert_eq!(location("//cdn.test/f").unwrap(), "https://cdn.test/f");
+        assert_eq!(location("http://x.test/y").unwrap(), "http://x.test/y");
+
+        let res = Response::builder()
+            .header("content-location", "/x")
+            .body(())
+            .unwrap();
+        assert_eq!(res.content_location(), None);
+    }
+}
@@ -18,8 +18,12 @@ use crate::util::private::Private;
 pub struct SendBody<'a> {
     inner: BodyInner<'a>,
     ended: bool,
+    sent: u64,
+    trailers: Option<Trailers<'a>>,
 }
 
+type Trailers<'a> = Box<dyn FnOnce() -> HeaderMap + 'a>;
+
 impl<'a> SendBody<'a> {
     /// Creates an empty body.
     pub fn none() -> SendBody<'static> {
@@ -49,6 +53,38 @@ impl<'a> SendBody<'a> {
         Ok(Self::from_owned_reader(io::Cursor::new(json)))
     }
 
+    /// Sends trailer headers after the body.
+    ///
+    /// The function is called once the body is fully read, which means the trailers
+    /// can hold values computed from the data, such as a checksum. Trailers are only
+    /// sent with chunked transfer encoding, i.e. for bodies of unknown size such as
+    /// from a reader.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use ureq::SendBody;
+    /// use ureq::http::HeaderMap;
+    ///
+    /// let mut reader = Cursor::new(b"hello world");
+    ///
+    /// let body = SendBody::from_reader(&mut reader).with_trailers(|| {
+    ///     let mut trailers = HeaderMap::new();
+    ///     trailers.insert("x-checksum", "5eb63bbb".parse().unwrap());
+    ///     trailers
+    /// });
+    ///
+    /// ureq::post("http://httpbin.org/post").send(body)?;
+    /// # Ok::<_, ureq::Error>(())
+    /// ```
+    pub fn with_trailers(mut self, trailers: impl FnOnce() -> HeaderMap + 'a) -> Self {
+        self.trailers = Some(Box::new(trailers));
+        self
+    }
+
+    pub(crate) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take().map(|f| f())
+    }
+
     pub(crate) fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let n = match &mut self.inner {
             BodyInner::None => {
@@ -71,6 +107,8 @@ impl<'a> SendBody<'a> {
             self.ended = true;
         }
 
+        self.sent += n as u64;
+
         Ok(n)
     }
 
@@ -78,13 +116,34 @@ impl<'a> SendBody<'a> {
         self.ended
     }
 
+    /// Number of body bytes handed over for sending so far.
+    pub(crate) fn sent(&self) -> u64 {
+        self.sent
+    }
+
     pub(crate) fn body_mode(&self) -> BodyMode {
         self.inner.body_mode()
     }
+
+    /// The body data, if it is in memory (not streamed from a reader).
+    pub(crate) fn as_slice(&self) -> Option<&[u8]> {
+        match &self.inner {
+            BodyInner::ByteSlice(v) => Some(v),
+            _ => None,
+        }
+    }
 }
 
 use hoot::BodyMode;
-use http::Response;
+use http::{HeaderMap, Response};
+
+/// Response extension with the number of request body bytes sent.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BodySent(pub u64);
+
+/// Response extension with a copy of the request body.
+#[derive(Debug, Clone)]
+pub(crate) struct SentBody(pub Vec<u8>);
 
 /// Trait for common types to send in POST, PUT or PATCH.
 ///
@@ -136,12 +195,12 @@ use http::Response;
 /// ```
 pub trait AsSendBody: Private {
     #[doc(hidden)]
-    fn as_body(&mut self) -> SendBody;
+    fn as_body(&mut self) -> SendBody<'_>;
 }
 
 impl<'a> Private for SendBody<'a> {}
 impl<'a> AsSendBody for SendBody<'a> {
-    fn as_body(&mut self) -> SendBody {
+    fn as_body(&mut self) -> SendBody<'_> {
         SendBody {
             inner: match &mut self.inner {
                 BodyInner::None => BodyInner::None,
@@ -151,10 +210,13 @@ impl<'a> AsSendBody for SendBody<'a> {
                 BodyInner::OwnedReader(v) => BodyInner::Reader(v),
             },
             ended: self.ended,
+            sent: self.sent,
+            trailers: self.trailers.take(),
         }
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum BodyInner<'a> {
     None,
     ByteSlice(&'a [u8]),
@@ -179,7 +241,7 @@ macro_rules! impl_into_body_slice {
     ($t:ty) => {
         impl Private for $t {}
         impl AsSendBody for $t {
-            fn as_body(&mut self) -> SendBody {
+            fn as_body(&mut self) -> SendBody<'_> {
                 BodyInner::ByteSlice((*self).as_ref()).into()
             }
         }
@@ -197,7 +259,7 @@ macro_rules! impl_into_body {
     ($t:ty, $s:tt) => {
         impl Private for $t {}
         impl AsSendBody for $t {
-            fn as_body(&mut self) -> SendBody {
+            fn as_body(&mut self) -> SendBody<'_> {
                 BodyInner::$s(self).into()
             }
         }
@@ -224,27 +286,29 @@ impl<'a> From<BodyInner<'a>> for SendBody<'a> {
         SendBody {
             inner,
             ended: false,
+            sent: 0,
+            trailers: None,
         }
     }
 }
 
 impl Private for Body {}
 impl AsSendBody for Body {
-    fn as_body(&mut self) -> SendBody {
+    fn as_body(&mut self) -> SendBody<'_> {
         BodyInner::Body(self.as_reader()).into()
     }
 }
 
 impl Private for Response<Body> {}
 impl AsSendBody for Response<Body> {
-    fn as_body(&mut self) -> SendBody {
+    fn as_body(&mut self) -> SendBody<'_> {
         BodyInner::Body(self.body_mut().as_reader()).into()
     }
 }
 
 impl<const N: usize> Private for &[u8; N] {}
 impl<const N: usize> AsSendBody for &[u8; N] {
-    fn as_body(&mut self) -> SendBody {
+    fn as_body(&mut self) -> SendBody<'_> {
         BodyInner::ByteSlice(self.as_slice()).into()
     }
 }
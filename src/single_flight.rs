@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+
+use http::{header, Extensions, HeaderMap, Response, StatusCode, Uri, Version};
+
+use crate::body::BufferedBody;
+use crate::util::HeaderMapExt;
+use crate::{Body, Error};
+
+/// Collapses concurrent identical GET requests into one.
+///
+/// The first caller for a key (the leader) makes the request. Callers arriving while
+/// that request is in flight wait for it, and share the response if it is fit to be
+/// shared. Otherwise they go on to make their own requests.
+#[derive(Default)]
+pub(crate) struct SingleFlight {
+    flights: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+#[derive(Default)]
+struct Flight {
+    // None while in flight. Some(None) when the response can't be shared.
+    outcome: Mutex<Option<Option<SharedResponse>>>,
+    done: Condvar,
+}
+
+#[derive(Clone)]
+struct SharedResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    extensions: Extensions,
+    body: BufferedBody,
+}
+
+impl SingleFlight {
+    pub fn run(
+        &self,
+        key: String,
+        max_body: u64,
+        call: impl FnOnce() -> Result<Response<Body>, Error>,
+    ) -> Result<Response<Body>, Error> {
+        let (flight, is_leader) = {
+            let mut flights = self.flights.lock().unwrap();
+            match flights.get(&key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    flights.insert(key.clone(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+
+        if !is_leader {
+            return match flight.wait() {
+                Some(shared) => {
+                    debug!("Use single flight response: {}", key);
+                    Ok(shared.to_response())
+                }
+                None => call(),
+            };
+        }
+
+        // Ensures waiters are released also if the call panics.
+        let mut guard = LeaderGuard {
+            single_flight: self,
+            key,
+            flight,
+            outcome: None,
+        };
+
+        let response = call()?;
+
+        if !is_shareable(&response, max_body) {
+            debug!("Single flight response not shareable: {}", guard.key);
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+
+        let shared = SharedResponse {
+            status: parts.status,
+            version: parts.version,
+            headers: parts.headers.clone(),
+            extensions: parts.extensions.clone(),
+            body: body.into_buffered()?,
+        };
+
+        let response = Response::from_parts(parts, shared.body.to_body());
+        guard.outcome = Some(shared);
+
+        Ok(response)
+    }
+}
+
+/// The key of a request, its URI and headers.
+///
+/// Requests with different headers, such as another `authorization` or `cookie`, might
+/// get different responses, and never share one.
+pub(crate) fn request_key(uri: &Uri, headers: &HeaderMap) -> String {
+    let mut key = uri.to_string();
+    for (name, value) in headers {
+        key.push('\n');
+        key.push_str(name.as_str());
+        key.push_str(": ");
+        key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+    }
+    key
+}
+
+/// Only successful responses, with a body of known and limited size, that are not
+/// marked as private or no-store, are shared.
+fn is_shareable(response: &Response<Body>, max_body: u64) -> bool {
+    if !response.status().is_success() {
+        return false;
+    }
+
+    let size_ok = response
+        .body()
+        .known_size()
+        .map(|v| v <= max_body)
+        .unwrap_or(false);
+
+    if !size_ok {
+        return false;
+    }
+
+    let no_store = response
+        .headers()
+        .get_list(header::CACHE_CONTROL)
+        .iter()
+        .any(|v| v.eq_ignore_ascii_case("no-store") || v.eq_ignore_ascii_case("private"));
+
+    !no_store
+}
+
+impl SharedResponse {
+    fn to_response(&self) -> Response<Body> {
+        let mut response = Response::new(self.body.to_body());
+        *response.status_mut() = self.status;
+        *response.version_mut() = self.version;
+        *response.headers_mut() = self.headers.clone();
+        *response.extensions_mut() = self.extensions.clone();
+        response
+    }
+}
+
+impl Flight {
+    fn wait(&self) -> Option<SharedResponse> {
+        let mut outcome = self.outcome.lock().unwrap();
+        loop {
+            if let Some(v) = &*outcome {
+                return v.clone();
+            }
+            outcome = self.done.wait(outcome).unwrap();
+        }
+    }
+}
+
+struct LeaderGuard<'a> {
+    single_flight: &'a SingleFlight,
+    key: String,
+    flight: Arc<Flight>,
+    outcome: Option<SharedResponse>,
+}
+
+impl<'a> Drop for LeaderGuard<'a> {
+    fn drop(&mut self) {
+        // Remove first, so no new waiters arrive after we publish the outcome.
+        if let Ok(mut flights) = self.single_flight.flights.lock() {
+            flights.remove(&self.key);
+        }
+
+        if let Ok(mut outcome) = self.flight.outcome.lock() {
+            *outcome = Some(self.outcome.take());
+        }
+
+        self.flight.done.notify_all();
+    }
+}
+
+impl fmt::Debug for SingleFlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleFlight").finish()
+    }
+}
+
+#[cfg(all(test, feature = "_test"))]
+mod test {
+    use std::sync::Barrier;
+    use std::thread;
+
+    use crate::test::init_test_log;
+    use crate::transport::connection_count;
+    use crate::{Agent, AgentConfig, ResponseExt};
+
+    use super::*;
+
+    #[test]
+    fn concurrent_gets_share_response() {
+        init_test_log();
+
+        let agent: Agent = AgentConfig {
+            single_flight: Some(1024),
+            ..Default::default()
+        }
+        .into();
+
+        let barrier = Arc::new(Barrier::new(4));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let agent = agent.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    let mut res = agent.get("https://my.test/delay").call().unwrap();
+                    let body = res.body_mut().read_to_string().unwrap();
+                    (body, connection_count())
+                })
+            })
+            .collect();
+
+        let mut connections = 0;
+        for handle in handles {
+            let (body, count) = handle.join().unwrap();
+            assert_eq!(body, "delayed");
+            connections += count;
+        }
+
+        assert_eq!(connections, 1);
+    }
+
+    #[test]
+    fn shared_response_keeps_extensions() {
+        init_test_log();
+
+        let agent: Agent = AgentConfig {
+            single_flight: Some(1024),
+            ..Default::default()
+        }
+        .into();
+
+        let barrier = Arc::new(Barrier::new(2));
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let agent = agent.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    let res = agent.get("https://my.test/delay").call().unwrap();
+                    res.elapsed()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            // Elapsed is a response extension, lost if not carried to the waiter.
+            assert!(handle.join().unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn different_headers_are_not_shared() {
+        let uri: Uri = "https://my.test/delay".parse().unwrap();
+
+        let mut a = HeaderMap::new();
+        a.insert("authorization", "user a".parse().unwrap());
+        let mut b = HeaderMap::new();
+        b.insert("authorization", "user b".parse().unwrap());
+
+        assert_ne!(request_key(&uri, &a), request_key(&uri, &b));
+        assert_ne!(request_key(&uri, &a), request_key(&uri, &HeaderMap::new()));
+        assert_eq!(request_key(&uri, &a), request_key(&uri, &a.clone()));
+    }
+}
@@ -151,7 +151,7 @@ impl<'a> PrivateKey<'a> {
 ///
 /// The data may contain one or many PEM items. The iterator produces the recognized PEM
 /// items and skip others.
-pub fn parse_pem(pem: &[u8]) -> impl Iterator<Item = Result<PemItem, Error>> + '_ {
+pub fn parse_pem(pem: &[u8]) -> impl Iterator<Item = Result<PemItem<'_>, Error>> + '_ {
     PemIter(pem)
 }
 
@@ -20,7 +20,7 @@ pub use self::native_tls::NativeTlsConnector;
 /// Defaults to [`Rustls`][Self::Rustls] because this has the highest chance
 /// to compile and "just work" straight out of the box without installing additional
 /// development dependencies.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[non_exhaustive]
 pub enum TlsProvider {
     /// [Rustls](https://crates.io/crates/rustls) with the
@@ -30,6 +30,7 @@ pub enum TlsProvider {
     /// Requires the feature flag **rustls**.
     ///
     /// This is the default.
+    #[default]
     Rustls,
 
     /// [Native-TLS](https://crates.io/crates/native-tls) for cases where it's important to
@@ -96,6 +97,36 @@ pub struct TlsConfig {
     /// This breaks encryption and leaks secrets. Must never be enabled for code where
     /// any level of security is required.
     pub disable_verification: bool,
+
+    /// The lowest TLS version to accept.
+    ///
+    /// Connecting to a server that doesn't offer an allowed version fails.
+    /// **native-tls** can't require TLS 1.3 and errors for [`TlsVersion::Tls13`].
+    ///
+    /// Defaults to `None`, which is whatever the TLS provider allows.
+    pub min_version: Option<TlsVersion>,
+
+    /// The highest TLS version to accept.
+    ///
+    /// Defaults to `None`, which is whatever the TLS provider allows.
+    pub max_version: Option<TlsVersion>,
+}
+
+impl TlsConfig {
+    pub(crate) fn allows_version(&self, version: TlsVersion) -> bool {
+        self.min_version.map_or(true, |v| version >= v)
+            && self.max_version.map_or(true, |v| version <= v)
+    }
+}
+
+/// TLS protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum TlsVersion {
+    /// TLS 1.2
+    Tls12,
+    /// TLS 1.3
+    Tls13,
 }
 
 /// Configuration setting for root certs.
@@ -127,12 +158,8 @@ impl Default for TlsConfig {
             root_certs: RootCerts::PlatformVerifier,
             use_sni: true,
             disable_verification: false,
+            min_version: None,
+            max_version: None,
         }
     }
 }
-
-impl Default for TlsProvider {
-    fn default() -> Self {
-        Self::Rustls
-    }
-}
@@ -3,12 +3,12 @@ use std::fmt;
 use std::io::{Read, Write};
 use std::sync::Arc;
 
-use crate::tls::{RootCerts, TlsProvider};
+use crate::tls::{RootCerts, TlsProvider, TlsVersion};
 use crate::transport::time::NextTimeout;
 use crate::{transport::*, Error};
 use der::pem::LineEnding;
 use der::Document;
-use native_tls::{Certificate, HandshakeError, Identity, TlsConnector};
+use native_tls::{Certificate, HandshakeError, Identity, Protocol, TlsConnector};
 use native_tls::{TlsConnectorBuilder, TlsStream};
 use once_cell::sync::OnceCell;
 
@@ -127,6 +127,19 @@ fn build_connector(tls_config: &TlsConfig) -> Result<Arc<TlsConnector>, Error> {
         builder.identity(identity);
     }
 
+    // native-tls has no TLS 1.3 protocol, setting none means no limit.
+    match tls_config.min_version {
+        Some(TlsVersion::Tls13) => return Err(Error::Tls("native-tls can't require TLS 1.3")),
+        Some(TlsVersion::Tls12) => {
+            builder.min_protocol_version(Some(Protocol::Tlsv12));
+        }
+        None => {}
+    }
+
+    if tls_config.max_version == Some(TlsVersion::Tls12) {
+        builder.max_protocol_version(Some(Protocol::Tlsv12));
+    }
+
     builder.use_sni(tls_config.use_sni);
 
     if !tls_config.use_sni {
@@ -232,7 +245,7 @@ impl LazyStream {
                 })?;
                 *self = LazyStream::Started(stream);
                 // Next time we hit the other match arm
-                return self.handshaken();
+                self.handshaken()
             }
             LazyStream::Started(v) => Ok(v),
         }
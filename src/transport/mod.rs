@@ -31,7 +31,7 @@ use crate::resolver::{ResolvedSocketAddrs, Resolver};
 use crate::{AgentConfig, Error};
 
 pub use self::tcp::TcpConnector;
-use self::time::{Instant, NextTimeout};
+use self::time::{Duration, Instant, NextTimeout};
 
 mod buf;
 pub(crate) use buf::NoBuffers;
@@ -46,9 +46,13 @@ mod chain;
 pub use chain::ChainedConnector;
 
 #[cfg(any(test, feature = "_test"))]
+#[cfg_attr(not(feature = "_test"), allow(dead_code))]
 mod test;
 #[cfg(any(test, feature = "_test"))]
-pub use test::set_handler;
+pub use test::{
+    connection_count, last_request, record_requests, recorded_requests, set_handler,
+    set_handler_delivery, set_handler_version, Delivery, RecordedRequest,
+};
 
 #[cfg(feature = "socks-proxy")]
 mod socks;
@@ -124,7 +128,9 @@ pub struct ConnectionDetails<'a> {
     pub now: Instant,
 
     /// The next timeout for making the connection.
-    // TODO(martin): Make mechanism to lower duration for each step in the connector chain.
+    ///
+    /// This is the timeout as of [`ConnectionDetails::now`]. Connectors doing several
+    /// blocking steps should use [`ConnectionDetails::remaining_timeout()`].
     pub timeout: NextTimeout,
 }
 
@@ -142,6 +148,27 @@ impl<'a> ConnectionDetails<'a> {
 
         self.uri.scheme() == Some(&Scheme::HTTPS)
     }
+
+    /// The timeout left for making the connection.
+    ///
+    /// This is [`ConnectionDetails::timeout`] minus the time passed since
+    /// [`ConnectionDetails::now`]. Errors with [`Error::Timeout`] if there is no time left.
+    pub fn remaining_timeout(&self) -> Result<NextTimeout, Error> {
+        let elapsed = Instant::now().duration_since(self.now);
+
+        let after = match self.timeout.after {
+            Duration::Exact(v) => match v.checked_sub(*elapsed) {
+                Some(left) if !left.is_zero() => Duration::Exact(left),
+                _ => return Err(Error::Timeout(self.timeout.reason)),
+            },
+            Duration::NotHappening => Duration::NotHappening,
+        };
+
+        Ok(NextTimeout {
+            after,
+            reason: self.timeout.reason,
+        })
+    }
 }
 
 /// Transport of HTTP/1.1 as created by a [`Connector`].
@@ -43,11 +43,12 @@ impl Connector for SocksConnector {
             return Ok(chained);
         }
 
+        let timeout = details.remaining_timeout()?;
         let proxy_addrs = details
             .resolver
-            .resolve(proxy.uri(), details.config, details.timeout)?;
+            .resolve(proxy.uri(), details.config, timeout)?;
 
-        let stream = try_connect(&proxy_addrs, &details.addrs, proxy, details.timeout)?;
+        let stream = try_connect(&proxy_addrs, details, proxy)?;
 
         if details.config.no_delay {
             stream.set_nodelay(true)?;
@@ -65,12 +66,14 @@ impl Connector for SocksConnector {
 
 fn try_connect(
     proxy_addrs: &ResolvedSocketAddrs,
-    target_addrs: &ResolvedSocketAddrs,
+    details: &ConnectionDetails,
     proxy: &Proxy,
-    timeout: NextTimeout,
 ) -> Result<TcpStream, Error> {
-    for target_addr in target_addrs {
+    for target_addr in &details.addrs {
         for proxy_addr in proxy_addrs {
+            // Each attempt only gets what is left of the timeout.
+            let timeout = details.remaining_timeout()?;
+
             trace!(
                 "Try connect {} {} -> {}",
                 proxy.proto(),
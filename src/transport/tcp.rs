@@ -2,7 +2,7 @@ use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpStream};
 use std::{fmt, io, time};
 
-use crate::resolver::ResolvedSocketAddrs;
+use crate::proxy::ProxyUnreachable;
 use crate::transport::time::{Duration, NextTimeout};
 use crate::util::IoResultExt;
 use crate::{AgentConfig, Error};
@@ -27,7 +27,16 @@ impl Connector for TcpConnector {
         }
 
         let config = &details.config;
-        let stream = try_connect(&details.addrs, details.timeout, config)?;
+
+        let stream = match try_connect(details) {
+            Ok(v) => v,
+            // For a CONNECT proxy the addrs are those of the proxy. Tell the agent it
+            // can't be reached, since it might go direct instead.
+            Err(e) if config.proxy_fallback_direct && config.connect_proxy_uri().is_some() => {
+                return Err(ProxyUnreachable::wrap(e))
+            }
+            Err(e) => return Err(e),
+        };
 
         let buffers = LazyBuffers::new(config.input_buffer_size, config.output_buffer_size);
         let transport = TcpTransport::new(stream, buffers);
@@ -36,18 +45,20 @@ impl Connector for TcpConnector {
     }
 }
 
-fn try_connect(
-    addrs: &ResolvedSocketAddrs,
-    timeout: NextTimeout,
-    config: &AgentConfig,
-) -> Result<TcpStream, Error> {
-    for addr in addrs {
-        match try_connect_single(*addr, timeout, config) {
+fn try_connect(details: &ConnectionDetails) -> Result<TcpStream, Error> {
+    let count = details.addrs.len();
+    let mut last_err = None;
+
+    for (i, addr) in details.addrs.iter().enumerate() {
+        let timeout = attempt_timeout(details, count - i)?;
+
+        match try_connect_single(*addr, timeout, details.config) {
             // First that connects
             Ok(v) => return Ok(v),
-            // Intercept ConnectionRefused to try next addrs
-            Err(Error::Io(e)) if e.kind() == io::ErrorKind::ConnectionRefused => {
-                trace!("{} connection refused", addr);
+            // Intercept connection errors and timeouts to try next addrs
+            Err(e @ Error::Io(_)) | Err(e @ Error::Timeout(_)) => {
+                trace!("{} failed to connect: {}", addr, e);
+                last_err = Some(e);
                 continue;
             }
             // Other errors bail
@@ -56,10 +67,24 @@ fn try_connect(
     }
 
     debug!("Failed to connect to any resolved address");
-    Err(Error::Io(io::Error::new(
-        io::ErrorKind::ConnectionRefused,
-        "Connection refused",
-    )))
+    Err(last_err.unwrap_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "Connection refused",
+        ))
+    }))
+}
+
+/// The timeout for connecting to one address, with `addrs_left` addresses still to try.
+///
+/// Each attempt only gets what is left of the timeout, shared evenly with the addresses
+/// still to try. That way an unresponsive address can't use it all up.
+fn attempt_timeout(details: &ConnectionDetails, addrs_left: usize) -> Result<NextTimeout, Error> {
+    let mut timeout = details.remaining_timeout()?;
+    if let Duration::Exact(v) = timeout.after {
+        timeout.after = Duration::Exact(v / addrs_left as u32);
+    }
+    Ok(timeout)
 }
 
 fn try_connect_single(
@@ -210,3 +235,149 @@ impl fmt::Debug for TcpTransport {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::TcpListener;
+
+    use http::Uri;
+
+    use crate::resolver::DefaultResolver;
+    use crate::transport::time::Instant;
+    use crate::TimeoutReason;
+
+    use super::*;
+
+    #[test]
+    fn connect_respects_time_already_spent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let uri: Uri = format!("http://{}/", addr).parse().unwrap();
+        let config = AgentConfig::default();
+        let resolver = DefaultResolver::default();
+
+        // The connect phase started 2s ago with a 1s timeout, such as when
+        // DNS or an earlier attempt was slow. The listener would accept, but
+        // there is no time left to try.
+        let details = ConnectionDetails {
+            uri: &uri,
+            addrs: std::iter::once(addr).collect(),
+            config: &config,
+            resolver: &resolver,
+            now: Instant::now() - Duration::from_secs(2),
+            timeout: NextTimeout {
+                after: Duration::from_secs(1),
+                reason: TimeoutReason::OpenConnection,
+            },
+        };
+
+        let err = TcpConnector::default().connect(&details, None).unwrap_err();
+
+        assert!(matches!(err, Error::Timeout(TimeoutReason::OpenConnection)));
+    }
+
+    #[test]
+    fn connect_falls_back_to_next_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let uri: Uri = format!("http://{}/", addr).parse().unwrap();
+        let config = AgentConfig::default();
+        let resolver = DefaultResolver::default();
+
+        // The first address is a port nothing listens on, so it's refused.
+        let refused = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let details = ConnectionDetails {
+            uri: &uri,
+            addrs: [refused, addr].iter().copied().collect(),
+            config: &config,
+            resolver: &resolver,
+            now: Instant::now(),
+            timeout: NextTimeout {
+                after: Duration::from_secs(2),
+                reason: TimeoutReason::OpenConnection,
+            },
+        };
+
+        let transport = TcpConnector::default().connect(&details, None).unwrap();
+
+        assert!(transport.is_some());
+    }
+
+    #[test]
+    fn attempt_timeout_shares_what_is_left() {
+        let uri: Uri = "http://my.test/".parse().unwrap();
+        let config = AgentConfig::default();
+        let resolver = DefaultResolver::default();
+
+        // 4s to connect, of which an earlier address already used 1s.
+        let details = ConnectionDetails {
+            uri: &uri,
+            addrs: std::iter::empty().collect(),
+            config: &config,
+            resolver: &resolver,
+            now: Instant::now() - Duration::from_secs(1),
+            timeout: NextTimeout {
+                after: Duration::from_secs(4),
+                reason: TimeoutReason::OpenConnection,
+            },
+        };
+
+        // The remaining (at most) 3s, shared by the 3 addresses left to try.
+        let timeout = attempt_timeout(&details, 3).unwrap();
+        assert!(timeout.after <= Duration::from_secs(1));
+        assert!(*timeout.after > time::Duration::from_millis(500));
+        assert_eq!(timeout.reason, TimeoutReason::OpenConnection);
+
+        // The last address gets all that is left.
+        let timeout = attempt_timeout(&details, 1).unwrap();
+        assert!(timeout.after <= Duration::from_secs(3));
+        assert!(timeout.after > Duration::from_secs(2));
+    }
+
+    #[test]
+    fn connect_proxy_failure_is_unreachable() {
+        use crate::proxy::ProxyUnreachable;
+        use crate::Proxy;
+
+        // Nothing listens on the port once the listener is dropped.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let uri: Uri = "http://my.test/".parse().unwrap();
+        let proxy = Proxy::new(&format!("http://{}", addr)).unwrap();
+        let resolver = DefaultResolver::default();
+
+        let connect = |fallback: bool| {
+            let config = AgentConfig {
+                proxy: Some(proxy.clone()),
+                proxy_fallback_direct: fallback,
+                ..Default::default()
+            };
+            let details = ConnectionDetails {
+                uri: &uri,
+                addrs: std::iter::once(addr).collect(),
+                config: &config,
+                resolver: &resolver,
+                now: Instant::now(),
+                timeout: NextTimeout {
+                    after: Duration::from_secs(2),
+                    reason: TimeoutReason::OpenConnection,
+                },
+            };
+            TcpConnector::default().connect(&details, None).unwrap_err()
+        };
+
+        assert!(ProxyUnreachable::unwrap(connect(false)).is_err());
+
+        let err = ProxyUnreachable::unwrap(connect(true)).unwrap();
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -1,13 +1,13 @@
 #![allow(clippy::type_complexity)]
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io::Write;
 use std::io::{BufRead, BufReader};
 use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
 use std::{fmt, io, thread};
 
-use http::{Method, Request, Uri};
+use http::{HeaderMap, Method, Request, Uri};
 
 use crate::transport::time::{Duration, NextTimeout};
 use crate::Error;
@@ -18,6 +18,9 @@ use super::{Buffers, ConnectionDetails, Connector, LazyBuffers, Transport};
 pub(crate) struct TestConnector;
 
 thread_local!(static HANDLERS: RefCell<Vec<TestHandler>> = const { RefCell::new(Vec::new()) });
+thread_local!(static CONNECTIONS: Cell<usize> = const { Cell::new(0) });
+thread_local!(static RECORDING: Cell<bool> = const { Cell::new(false) });
+thread_local!(static REQUESTS: Arc<Mutex<Vec<RecordedRequest>>> = Arc::new(Mutex::new(Vec::new())));
 
 impl Connector for TestConnector {
     fn connect(
@@ -32,6 +35,8 @@ impl Connector for TestConnector {
         }
         let config = details.config;
 
+        CONNECTIONS.with(|c| c.set(c.get() + 1));
+
         let uri = details.uri.clone();
 
         let buffers = LazyBuffers::new(config.input_buffer_size, config.output_buffer_size);
@@ -42,12 +47,17 @@ impl Connector for TestConnector {
         let mut handlers = HANDLERS.with(|h| (*h).borrow().clone());
         setup_default_handlers(&mut handlers);
 
-        thread::spawn(|| test_run(uri, rx1, tx2, handlers));
+        let requests = RECORDING
+            .with(|r| r.get())
+            .then(|| REQUESTS.with(|r| r.clone()));
+
+        thread::spawn(|| test_run(uri, rx1, tx2, handlers, requests));
 
         let transport = TestTransport {
             buffers,
             tx: tx1,
             rx: SyncReceiver(Mutex::new(rx2)),
+            pending: Vec::new(),
             connected: true,
         };
 
@@ -69,6 +79,54 @@ impl TestHandler {
 
 /// Helper for **_test** feature tests.
 pub fn set_handler(pattern: &'static str, status: u16, headers: &[(&str, &str)], body: &[u8]) {
+    set_handler_version(pattern, "HTTP/1.1", status, headers, body)
+}
+
+/// Like [`set_handler`], but responding with the given HTTP version.
+pub fn set_handler_version(
+    pattern: &'static str,
+    version: &'static str,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) {
+    add_handler(pattern, version, status, headers, body, Delivery::Whole)
+}
+
+/// How the test server delivers a response.
+///
+/// Helper for **_test** feature tests of timeouts and short reads.
+#[derive(Debug, Clone, Copy)]
+pub enum Delivery {
+    /// The whole response in one go.
+    Whole,
+    /// The headers in one go, then the body byte by byte with a delay before each.
+    Slow(std::time::Duration),
+    /// The headers, but never the body.
+    StallAfterHeaders,
+    /// The headers and this many bytes of the body, then drop the connection.
+    DropAfter(usize),
+}
+
+/// Like [`set_handler`], but delivering the response as given.
+pub fn set_handler_delivery(
+    pattern: &'static str,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    delivery: Delivery,
+) {
+    add_handler(pattern, "HTTP/1.1", status, headers, body, delivery)
+}
+
+fn add_handler(
+    pattern: &'static str,
+    version: &'static str,
+    status: u16,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    delivery: Delivery,
+) {
     // Convert headers to a big string
     let mut headers_s = String::new();
     for (k, v) in headers {
@@ -79,19 +137,98 @@ pub fn set_handler(pattern: &'static str, status: u16, headers: &[(&str, &str)],
     let body = body.to_vec();
 
     let handler = TestHandler::new(pattern, move |_uri, _req, w| {
-        write!(
-            w,
-            "HTTP/1.1 {} OK\r\n\
+        let head = format!(
+            "{} {} OK\r\n\
             {}\
             \r\n",
-            status, headers_s
-        )?;
-        w.write_all(&body)
+            version, status, headers_s
+        );
+
+        match delivery {
+            Delivery::Whole => {
+                // Send the response in one go, like a server would typically do it.
+                let mut response = head.into_bytes();
+                response.extend_from_slice(&body);
+                w.write_all(&response)
+            }
+            Delivery::Slow(delay) => {
+                w.write_all(head.as_bytes())?;
+                for b in &body {
+                    thread::sleep(delay);
+                    w.write_all(&[*b])?;
+                }
+                Ok(())
+            }
+            Delivery::StallAfterHeaders => {
+                w.write_all(head.as_bytes())?;
+                // Hold the connection open until the client has long given up.
+                thread::sleep(std::time::Duration::from_secs(10));
+                Ok(())
+            }
+            Delivery::DropAfter(n) => {
+                let mut response = head.into_bytes();
+                response.extend_from_slice(&body[..n.min(body.len())]);
+                w.write_all(&response)
+            }
+        }
     });
 
     HANDLERS.with(|h| (*h).borrow_mut().push(handler));
 }
 
+/// Number of connections opened to the test server by the current thread.
+///
+/// Helper for **_test** feature tests to tell a reused connection from a new one.
+pub fn connection_count() -> usize {
+    CONNECTIONS.with(|c| c.get())
+}
+
+/// A request as received by the test server.
+///
+/// Helper for **_test** feature tests to check what the client sent.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// The request method.
+    pub method: Method,
+    /// The request uri.
+    pub uri: Uri,
+    /// The request headers.
+    pub headers: HeaderMap,
+    /// The request line and headers exactly as sent, up to and including the empty line.
+    ///
+    /// Unlike `headers`, this keeps the order headers were sent in.
+    pub head: Vec<u8>,
+    /// The request body, with any chunked framing removed.
+    pub body: Vec<u8>,
+}
+
+/// Record the requests received by the test server on connections from the current thread.
+///
+/// Helper for **_test** feature tests. Recording reads the request body before the handler
+/// runs, so it's off unless a test asks for it.
+pub fn record_requests() {
+    RECORDING.with(|r| r.set(true));
+}
+
+/// The last request received by the test server on connections from the current thread.
+///
+/// Requires [`record_requests()`].
+///
+/// Helper for **_test** feature tests.
+pub fn last_request() -> Option<RecordedRequest> {
+    REQUESTS.with(|r| r.lock().unwrap().last().cloned())
+}
+
+/// All requests received by the test server on connections from the current thread,
+/// in order.
+///
+/// Requires [`record_requests()`].
+///
+/// Helper for **_test** feature tests.
+pub fn recorded_requests() -> Vec<RecordedRequest> {
+    REQUESTS.with(|r| r.lock().unwrap().clone())
+}
+
 #[derive(Clone)]
 struct TestHandler {
     pattern: &'static str,
@@ -103,22 +240,38 @@ fn test_run(
     rx: Receiver<Vec<u8>>,
     tx: mpsc::SyncSender<Vec<u8>>,
     handlers: Vec<TestHandler>,
+    requests: Option<Arc<Mutex<Vec<RecordedRequest>>>>,
 ) {
     let mut reader = BufReader::new(RxRead(rx));
     let mut writer = TxWrite(tx);
     let uri_s = uri.to_string();
 
-    let req = loop {
+    let (req, head) = loop {
         let input = reader.fill_buf().expect("test fill_buf");
         let maybe = hoot::parser::try_parse_request::<100>(input).expect("test parse request");
         if let Some((amount, req)) = maybe {
+            let head = input[..amount].to_vec();
             reader.consume(amount);
-            break req;
+            break (req, head);
         } else {
             continue;
         }
     };
 
+    // Recorded before responding, so the request is there once the client has a response.
+    // Reading the body holds back the response until the client has sent it all, so
+    // it's only done for tests that record.
+    if let Some(requests) = requests {
+        let body = read_request_body(&mut reader, &req);
+        requests.lock().unwrap().push(RecordedRequest {
+            method: req.method().clone(),
+            uri: uri.clone(),
+            headers: req.headers().clone(),
+            head,
+            body,
+        });
+    }
+
     for handler in handlers {
         if uri_s.contains(handler.pattern) {
             (handler.handler)(uri, req, &mut writer).expect("test handler to not fail");
@@ -129,6 +282,57 @@ fn test_run(
     panic!("test server unhandled url: {}", uri);
 }
 
+fn read_request_body(reader: &mut impl BufRead, req: &Request<()>) -> Vec<u8> {
+    let headers = req.headers();
+
+    // The client holds back the body until a 100 Continue, which the test server doesn't send.
+    if headers.contains_key("expect") {
+        return vec![];
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let is_chunked = headers
+        .get("transfer-encoding")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("chunked"))
+        .unwrap_or(false);
+
+    let mut body = vec![];
+
+    // Read errors mean the client went away, which leaves the body short.
+    if let Some(len) = content_length {
+        let _ = io::Read::read_to_end(&mut io::Read::take(&mut *reader, len), &mut body);
+    } else if is_chunked {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                break;
+            }
+            let size = line.trim().split(';').next().unwrap_or("");
+            let Ok(size) = u64::from_str_radix(size, 16) else {
+                break;
+            };
+            if size == 0 {
+                // Skip trailers up until the empty line.
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                break;
+            }
+            let _ = io::Read::read_to_end(&mut io::Read::take(&mut *reader, size), &mut body);
+            line.clear();
+            let _ = reader.read_line(&mut line);
+        }
+    }
+
+    body
+}
+
 fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
     fn maybe_add(handler: TestHandler, handlers: &mut Vec<TestHandler>) {
         let already_declared = handlers.iter().any(|h| h.pattern == handler.pattern);
@@ -185,7 +389,7 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
                 Content-Type: application/json\r\n\
                 Content-Length: {}\r\n\
                 \r\n",
-                HTTPBIN_GET.as_bytes().len()
+                HTTPBIN_GET.len()
             )?;
             if req.method() != Method::HEAD {
                 w.write_all(HTTPBIN_GET.as_bytes())?;
@@ -195,6 +399,75 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
         handlers,
     );
 
+    maybe_add(
+        TestHandler::new("/echo_headers", |_uri, req, w| {
+            // One line per received header, in the order they were sent.
+            let mut body = String::new();
+            for (k, v) in req.headers() {
+                body.push_str(&format!("{}: {}\n", k, v.to_str().unwrap_or("")));
+            }
+            write!(
+                w,
+                "HTTP/1.1 200 OK\r\n\
+                Content-Type: text/plain\r\n\
+                Content-Length: {}\r\n\
+                \r\n\
+                {}",
+                body.len(),
+                body
+            )
+        }),
+        handlers,
+    );
+
+    maybe_add(
+        TestHandler::new("/partial", |uri, req, w| {
+            // Serves at most 5 bytes per response, from the start of the requested range.
+            // The "_broken" variant ignores the range and always starts over.
+            const DATA: &[u8] = b"hello world!";
+            let start = req
+                .headers()
+                .get("range")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("bytes="))
+                .and_then(|v| v.trim_end_matches('-').parse().ok())
+                .filter(|_| !uri.path().ends_with("_broken"))
+                .unwrap_or(0);
+            let end = (start + 5).min(DATA.len());
+            write!(
+                w,
+                "HTTP/1.1 206 Partial Content\r\n\
+                Connection: close\r\n\
+                ETag: \"v1\"\r\n\
+                Content-Range: bytes {}-{}/{}\r\n\
+                Content-Length: {}\r\n\
+                \r\n",
+                start,
+                end - 1,
+                DATA.len(),
+                end - start
+            )?;
+            w.write_all(&DATA[start..end])
+        }),
+        handlers,
+    );
+
+    maybe_add(
+        TestHandler::new("/delay", |_uri, _req, w| {
+            // A slow server, to have requests overlap in time.
+            thread::sleep(std::time::Duration::from_millis(200));
+            write!(
+                w,
+                "HTTP/1.1 200 OK\r\n\
+                Content-Type: text/plain\r\n\
+                Content-Length: 7\r\n\
+                \r\n\
+                delayed"
+            )
+        }),
+        handlers,
+    );
+
     maybe_add(
         TestHandler::new("/head", |_uri, _req, w| {
             write!(
@@ -203,7 +476,7 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
                 Content-Type: application/json\r\n\
                 Content-Length: {}\r\n\
                 \r\n",
-                HTTPBIN_GET.as_bytes().len()
+                HTTPBIN_GET.len()
             )
         }),
         handlers,
@@ -217,7 +490,7 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
                 Content-Type: application/json\r\n\
                 Content-Length: {}\r\n\
                 \r\n",
-                HTTPBIN_PUT.as_bytes().len()
+                HTTPBIN_PUT.len()
             )?;
             w.write_all(HTTPBIN_PUT.as_bytes())
         }),
@@ -232,7 +505,7 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
                     Content-Type: application/json\r\n\
                     Content-Length: {}\r\n\
                     \r\n",
-                HTTPBIN_PUT.as_bytes().len()
+                HTTPBIN_PUT.len()
             )?;
             w.write_all(HTTPBIN_PUT.as_bytes())
         }),
@@ -262,13 +535,37 @@ fn setup_default_handlers(handlers: &mut Vec<TestHandler>) {
                 Content-Type: application/json\r\n\
                 Content-Length: {}\r\n\
                 \r\n",
-                HTTPBIN_JSON.as_bytes().len()
+                HTTPBIN_JSON.len()
             )?;
             w.write_all(HTTPBIN_JSON.as_bytes())
         }),
         handlers,
     );
 
+    maybe_add(
+        TestHandler::new("/bare-lf", |_uri, _req, w| {
+            write!(
+                w,
+                "HTTP/1.1 200 OK\ncontent-type: text/plain\ncontent-length: 5\n\nhello"
+            )
+        }),
+        handlers,
+    );
+
+    maybe_add(
+        TestHandler::new("/no-reason", |_uri, _req, w| {
+            write!(w, "HTTP/1.1 200\r\ncontent-length: 5\r\n\r\nhello")
+        }),
+        handlers,
+    );
+
+    maybe_add(
+        TestHandler::new("/bad-status", |_uri, _req, w| {
+            write!(w, "HTTP/1.1 BORKED\r\ncontent-length: 0\r\n\r\n")
+        }),
+        handlers,
+    );
+
     #[cfg(feature = "charset")]
     {
         let (cow, _, _) =
@@ -374,6 +671,8 @@ struct TestTransport {
     buffers: LazyBuffers,
     tx: mpsc::SyncSender<Vec<u8>>,
     rx: SyncReceiver<Vec<u8>>,
+    // Received data that didn't fit in the input buffer.
+    pending: Vec<u8>,
     connected: bool,
 }
 
@@ -392,21 +691,23 @@ impl Transport for TestTransport {
 
     fn await_input(&mut self, timeout: NextTimeout) -> Result<bool, Error> {
         let input = self.buffers.input_mut();
-        let buf = match self.rx.recv_timeout(timeout.after) {
-            Ok(v) => v,
-            Err(RecvTimeoutError::Timeout) => return Err(Error::Timeout(timeout.reason)),
-            Err(RecvTimeoutError::Disconnected) => {
-                trace!("Test server disconnected");
-                self.connected = false;
-                return Err(Error::Io(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "test server disconnected",
-                )));
+        let buf = if !self.pending.is_empty() {
+            std::mem::take(&mut self.pending)
+        } else {
+            match self.rx.recv_timeout(timeout.after) {
+                Ok(v) => v,
+                Err(RecvTimeoutError::Timeout) => return Err(Error::Timeout(timeout.reason)),
+                Err(RecvTimeoutError::Disconnected) => {
+                    // Like a socket, signal the end of input by making no progress.
+                    trace!("Test server disconnected");
+                    self.connected = false;
+                    return Ok(false);
+                }
             }
         };
-        assert!(input.len() >= buf.len());
         let max = input.len().min(buf.len());
-        input[..max].copy_from_slice(&buf[..]);
+        input[..max].copy_from_slice(&buf[..max]);
+        self.pending = buf[max..].to_vec();
         self.buffers.add_filled(max);
         Ok(max > 0)
     }
@@ -1,5 +1,6 @@
 use core::fmt;
 use std::collections::VecDeque;
+use std::io::Write;
 use std::mem;
 use std::sync::Arc;
 
@@ -9,13 +10,15 @@ use hoot::client::flow::state::{
 };
 use hoot::client::flow::{Await100Result, RecvBodyResult, RecvResponseResult, SendRequestResult};
 use hoot::BodyMode;
-use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, Uri, Version};
+use http::{
+    header, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri, Version,
+};
 
 use crate::error::TimeoutReason;
 use crate::transport::time::{Instant, NextTimeout};
 use crate::transport::Buffers;
-use crate::util::{DebugHeaders, DebugUri};
-use crate::{AgentConfig, Error, SendBody, Timeouts};
+use crate::util::{DebugHeaders, DebugUri, HeaderMapExt};
+use crate::{AgentConfig, Error, RedirectAuthHeaders, SendBody, Timeouts};
 
 pub(crate) struct Unit<B> {
     config: Arc<AgentConfig>,
@@ -27,6 +30,23 @@ pub(crate) struct Unit<B> {
     queued_event: VecDeque<Event<'static>>,
     redirect_count: u32,
     prev_state: &'static str,
+    /// The connection is closed after the response. HTTP/1.0 responses close the
+    /// connection unless the server asks for keep-alive, and ambiguous framing always.
+    close_connection: bool,
+    /// Uri of the current call, to compare with when following redirects.
+    current_uri: Uri,
+    /// Method of the current call, to tell when a redirect changes it.
+    current_method: Method,
+    /// The headers of the current call, if a redirect might need to restart the flow
+    /// with them. That is to put back the authorization, or to leave out the body
+    /// headers when the redirect changes the method to GET.
+    redirect_headers: Option<HeaderMap>,
+    /// Whether the authorization header is still sent. Cleared once a redirect
+    /// drops it.
+    keep_auth: bool,
+    /// Position in the input of the next response header line to report to
+    /// `on_header_line`. `None` once the end of the header is reported.
+    header_line_pos: Option<usize>,
 }
 
 type Flow<State> = hoot::client::flow::Flow<(), State>;
@@ -94,6 +114,16 @@ impl<'b> Unit<SendBody<'b>> {
         request: Request<()>,
         body: SendBody<'b>,
     ) -> Result<Self, Error> {
+        let current_uri = request.uri().clone();
+
+        let current_method = request.method().clone();
+
+        let keep_auth = config.redirect_auth_headers != RedirectAuthHeaders::Never
+            && request.headers().contains_key(header::AUTHORIZATION);
+        // Redirects only change methods other than GET and HEAD.
+        let method_may_change = !matches!(current_method, Method::GET | Method::HEAD);
+        let redirect_headers = (keep_auth || method_may_change).then(|| request.headers().clone());
+
         Ok(Self {
             config,
             timeouts,
@@ -104,16 +134,30 @@ impl<'b> Unit<SendBody<'b>> {
             queued_event: VecDeque::new(),
             redirect_count: 0,
             prev_state: "",
+            close_connection: false,
+            current_uri,
+            current_method,
+            redirect_headers,
+            keep_auth,
+            header_line_pos: Some(0),
         })
     }
 
-    pub fn poll_event(&mut self, now: Instant, buffers: &mut dyn Buffers) -> Result<Event, Error> {
+    pub fn poll_event(
+        &mut self,
+        now: Instant,
+        buffers: &mut dyn Buffers,
+    ) -> Result<Event<'_>, Error> {
         let event = self.do_poll_event(now, buffers)?;
         trace!("poll_event: {:?}", event);
         Ok(event)
     }
 
-    fn do_poll_event(&mut self, now: Instant, buffers: &mut dyn Buffers) -> Result<Event, Error> {
+    fn do_poll_event(
+        &mut self,
+        now: Instant,
+        buffers: &mut dyn Buffers,
+    ) -> Result<Event<'_>, Error> {
         // Queued events go first.
         if let Some(queued) = self.queued_event.pop_front() {
             return Ok(queued);
@@ -161,12 +205,17 @@ impl<'b> Unit<SendBody<'b>> {
 
             State::Redirect(flow) => {
                 // Whether the previous connection must be closed.
-                let must_close = flow.must_close_connection();
+                let must_close = flow.must_close_connection() || self.close_connection;
 
-                let maybe_new_flow = flow.as_new_flow(self.config.redirect_auth_headers)?;
+                // The authorization header is always removed here, and put back below
+                // if the redirect policy allows it.
+                let maybe_new_flow =
+                    flow.as_new_flow(hoot::client::flow::RedirectAuthHeaders::Never)?;
                 let status = flow.status();
 
                 if let Some(flow) = maybe_new_flow {
+                    let flow = self.redirect_flow(flow)?;
+
                     info!(
                         "Redirect ({}): {} {:?}",
                         status,
@@ -185,7 +234,7 @@ impl<'b> Unit<SendBody<'b>> {
             }
 
             State::Cleanup(flow) => Some(Event::Reset {
-                must_close: flow.must_close_connection(),
+                must_close: flow.must_close_connection() || self.close_connection,
             }),
 
             State::Empty => unreachable!("self.state should never be in State::Empty"),
@@ -241,7 +290,7 @@ impl<'b> Unit<SendBody<'b>> {
     }
 
     // These events borrow from the State, but they don't proceed the FSM.
-    fn poll_event_borrow(&self, timeout: NextTimeout) -> Result<Event, Error> {
+    fn poll_event_borrow(&self, timeout: NextTimeout) -> Result<Event<'_>, Error> {
         let event = match &self.state {
             State::Prepare(flow) => Event::Prepare { uri: flow.uri() },
 
@@ -332,18 +381,69 @@ impl<'b> Unit<SendBody<'b>> {
                         return Err(Error::disconnected());
                     }
 
-                    if input.len() > self.config.max_response_header_size {
+                    if let Some(on_line) = &self.config.on_header_line {
+                        self.header_line_pos =
+                            report_header_lines(input, self.header_line_pos, &**on_line);
+                    }
+
+                    let (input_used, maybe_response) =
+                        flow.try_response(input).map_err(|e| bad_status(input, e))?;
+
+                    let Some(mut response) = maybe_response else {
+                        // Consumed input moves the lines not yet reported.
+                        self.header_line_pos =
+                            self.header_line_pos.map(|p| p.saturating_sub(input_used));
+
+                        // The input might contain the beginning of the body as well, so
+                        // we only know the header is too big when it's not complete.
+                        if input.len() > self.config.max_response_header_size {
+                            return Err(Error::LargeResponseHeader(
+                                input.len(),
+                                self.config.max_response_header_size,
+                            ));
+                        }
+
+                        return Ok(input_used);
+                    };
+
+                    if input_used > self.config.max_response_header_size {
                         return Err(Error::LargeResponseHeader(
-                            input.len(),
+                            input_used,
                             self.config.max_response_header_size,
                         ));
                     }
 
-                    let (input_used, maybe_response) = flow.try_response(input)?;
+                    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+                        return Err(Error::UnsupportedUpgrade);
+                    }
 
-                    let Some(response) = maybe_response else {
-                        return Ok(input_used);
-                    };
+                    // Differing values make the body length ambiguous, which opens up
+                    // for request smuggling. Identical repeated values are fine.
+                    if response.headers().has_conflicting_content_length() {
+                        return Err(hoot::Error::TooManyContentLengthHeaders.into());
+                    }
+
+                    let headers = response.headers();
+                    let is_ambiguous =
+                        headers.is_chunked() && headers.contains_key(header::CONTENT_LENGTH);
+                    if is_ambiguous {
+                        if self.config.reject_ambiguous_framing {
+                            return Err(hoot::Error::BadHeader(
+                                "both transfer-encoding: chunked and content-length".to_string(),
+                            )
+                            .into());
+                        }
+
+                        // Transfer-encoding wins (RFC 9112 6.3). The content-length is
+                        // dropped, and the connection not reused, since the server and an
+                        // intermediary might disagree on where the response ends.
+                        info!("Response has both transfer-encoding: chunked and content-length");
+                        response.headers_mut().remove(header::CONTENT_LENGTH);
+                    }
+
+                    self.close_connection = is_ambiguous
+                        || response.version() == Version::HTTP_10
+                            && !response.headers().has_keep_alive();
 
                     let end = if response.status().is_redirection() {
                         self.redirect_count += 1;
@@ -367,6 +467,7 @@ impl<'b> Unit<SendBody<'b>> {
                     };
 
                     self.call_timings.time_recv_response = Some(now);
+                    self.header_line_pos = Some(0);
                     self.set_state(state);
 
                     return Ok(input_used);
@@ -392,6 +493,21 @@ impl<'b> Unit<SendBody<'b>> {
         });
     }
 
+    /// The uri of the current request, which changes when following redirects.
+    pub fn current_uri(&self) -> &Uri {
+        &self.current_uri
+    }
+
+    /// Whether the current request is made to follow a redirect. The request body is
+    /// never sent again for those.
+    pub fn is_redirect(&self) -> bool {
+        self.redirect_count > 0
+    }
+
+    pub fn body_sent(&self) -> u64 {
+        self.body.sent()
+    }
+
     pub fn release_body(self) -> Unit<()> {
         Unit {
             config: self.config,
@@ -402,7 +518,13 @@ impl<'b> Unit<SendBody<'b>> {
             body: (),
             queued_event: self.queued_event,
             redirect_count: self.redirect_count,
+            close_connection: self.close_connection,
             prev_state: self.prev_state,
+            current_uri: self.current_uri,
+            current_method: self.current_method,
+            redirect_headers: self.redirect_headers,
+            keep_auth: self.keep_auth,
+            header_line_pos: self.header_line_pos,
         }
     }
 
@@ -441,13 +563,13 @@ pub(crate) struct FakeRequest<'a> {
 
 // Unit<()> is for receiving the body. We have let go of the input body.
 impl Unit<()> {
-    pub fn poll_event(&mut self, now: Instant) -> Result<Event, Error> {
+    pub fn poll_event(&mut self, now: Instant) -> Result<Event<'_>, Error> {
         let event = self.do_poll_event(now)?;
         trace!("poll_event (recv): {:?}", event);
         Ok(event)
     }
 
-    fn do_poll_event(&mut self, now: Instant) -> Result<Event, Error> {
+    fn do_poll_event(&mut self, now: Instant) -> Result<Event<'_>, Error> {
         // Queued events go first.
         if let Some(queued) = self.queued_event.pop_front() {
             return Ok(queued);
@@ -458,10 +580,10 @@ impl Unit<()> {
         match &self.state {
             State::RecvBody(_) => Ok(Event::AwaitInput { timeout }),
             State::Cleanup(flow) => Ok(Event::Reset {
-                must_close: flow.must_close_connection(),
+                must_close: flow.must_close_connection() || self.close_connection,
             }),
             State::Redirect(flow) => Ok(Event::Reset {
-                must_close: flow.must_close_connection(),
+                must_close: flow.must_close_connection() || self.close_connection,
             }),
             _ => unreachable!(),
         }
@@ -494,6 +616,77 @@ impl<B> Unit<B> {
         self.state = state
     }
 
+    /// Restarts the redirected flow when hoot's request for the redirect needs amending.
+    ///
+    /// The authorization header is put back, if the redirect policy allows it. Each hop
+    /// is checked against the previous one. Once a hop drops the header, it is gone for
+    /// the rest of the redirects, also when they stay on the new origin.
+    ///
+    /// When the redirect changes the method to GET, the headers describing the body are
+    /// left out, since there is no body anymore.
+    ///
+    /// hoot can't take back a header it removed, hence the new flow. It's the request
+    /// hoot would make for the redirect, with the above amends.
+    fn redirect_flow(&mut self, flow: Flow<Prepare>) -> Result<Flow<Prepare>, Error> {
+        let prev_uri = mem::replace(&mut self.current_uri, flow.uri().clone());
+        let prev_method = mem::replace(&mut self.current_method, flow.method().clone());
+
+        if self.keep_auth
+            && !self
+                .config
+                .redirect_auth_headers
+                .keep(&prev_uri, flow.uri())
+        {
+            debug!("Remove authorization header on redirect");
+            self.keep_auth = false;
+        }
+
+        let method_changed = prev_method != flow.method();
+
+        let Some(headers) = &self.redirect_headers else {
+            return Ok(flow);
+        };
+
+        if !self.keep_auth && !method_changed {
+            return Ok(flow);
+        }
+
+        let mut request = Request::builder()
+            .method(flow.method().clone())
+            .uri(flow.uri().clone())
+            .version(flow.version())
+            .body(())
+            .expect("request from valid parts");
+
+        // Same headers as removed by hoot when redirecting.
+        let removed = [
+            header::AUTHORIZATION,
+            header::COOKIE,
+            header::CONTENT_LENGTH,
+        ];
+        let body_headers = [
+            header::CONTENT_TYPE,
+            header::CONTENT_ENCODING,
+            header::TRANSFER_ENCODING,
+        ];
+
+        for (name, value) in headers {
+            let drop = if name == header::AUTHORIZATION {
+                !self.keep_auth
+            } else {
+                removed.contains(name) || method_changed && body_headers.contains(name)
+            };
+            if !drop {
+                request.headers_mut().append(name, value.clone());
+            }
+        }
+
+        // Later redirects start from this request.
+        self.redirect_headers = Some(request.headers().clone());
+
+        Ok(Flow::new(request)?)
+    }
+
     fn global_timeout(&self) -> Instant {
         self.timeouts
             .global
@@ -508,14 +701,15 @@ impl<B> Unit<B> {
         let global_timeout_at = self.global_timeout();
         let global_timeout = global_timeout_at.duration_since(now);
 
-        let timeout = call_timeout.min(global_timeout);
+        // Whichever timeout happens first is the one we report.
+        let (timeout, reason) = if global_timeout <= call_timeout {
+            (global_timeout, TimeoutReason::Global)
+        } else {
+            (call_timeout, reason)
+        };
 
         if timeout.is_zero() {
-            return Err(Error::Timeout(if global_timeout.is_zero() {
-                TimeoutReason::Global
-            } else {
-                reason
-            }));
+            return Err(Error::Timeout(reason));
         }
 
         Ok(NextTimeout {
@@ -604,7 +798,13 @@ fn send_body(
         // the entire input we read from the body should also be shipped to the output.
         assert!(input_used == n);
 
-        output_used
+        // An ended body means the output ends with the final chunk.
+        let trailers = if n == 0 { body.take_trailers() } else { None };
+
+        match trailers {
+            Some(trailers) => write_trailers(output, output_used, &trailers)?,
+            None => output_used,
+        }
     };
 
     Ok(Event::Transmit {
@@ -613,6 +813,87 @@ fn send_body(
     })
 }
 
+/// Adds trailers to the final chunk, `0\r\n\r\n`, that ends `output[..used]`.
+///
+/// Returns the new amount of output used.
+fn write_trailers(output: &mut [u8], used: usize, trailers: &HeaderMap) -> Result<usize, Error> {
+    debug_assert!(output[..used].ends_with(b"0\r\n\r\n"));
+
+    // The trailers go between the chunk size line and the final empty line.
+    let mut w = &mut output[used - 2..];
+    let available = w.len();
+
+    let result = trailers
+        .iter()
+        .try_for_each(|(name, value)| {
+            w.write_all(name.as_str().as_bytes())?;
+            w.write_all(b": ")?;
+            w.write_all(value.as_bytes())?;
+            w.write_all(b"\r\n")
+        })
+        .and_then(|_| w.write_all(b"\r\n"));
+
+    if result.is_err() {
+        return Err(Error::Protocol(hoot::Error::OutputOverflow));
+    }
+
+    Ok(used - 2 + available - w.len())
+}
+
+/// Max bytes of a malformed status line to keep in [`Error::BadStatus`].
+const MAX_BAD_STATUS_LEN: usize = 100;
+
+/// Turns a parse error into [`Error::BadStatus`] if the status line is malformed.
+///
+/// A well formed status line means the error is elsewhere in the header, and the
+/// error is kept as is.
+fn bad_status(input: &[u8], e: hoot::Error) -> Error {
+    let line = match input.iter().position(|b| *b == b'\n') {
+        Some(n) => &input[..n],
+        None => input,
+    };
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    // The line is from the server, which could send a lot of it.
+    let line = &line[..line.len().min(MAX_BAD_STATUS_LEN)];
+    let line = String::from_utf8_lossy(line);
+
+    let mut parts = line.splitn(3, ' ');
+    let version_ok = matches!(parts.next(), Some("HTTP/1.0" | "HTTP/1.1"));
+    let status_ok = parts
+        .next()
+        .map(|v| v.len() == 3 && v.parse::<u16>().is_ok())
+        .unwrap_or(false);
+
+    if version_ok && status_ok {
+        return e.into();
+    }
+
+    debug!("Bad status line ({}): {:?}", e, line);
+    Error::BadStatus(line.into_owned())
+}
+
+/// Reports the complete lines of a response header in `input`, from `pos` on.
+///
+/// Returns the position of the next line to report, or `None` after the empty line
+/// that ends the header.
+fn report_header_lines(input: &[u8], pos: Option<usize>, on_line: &dyn Fn(&str)) -> Option<usize> {
+    let mut pos = pos?;
+
+    while let Some(n) = input.get(pos..)?.iter().position(|b| *b == b'\n') {
+        let line = &input[pos..pos + n];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        pos += n + 1;
+
+        if line.is_empty() {
+            return None;
+        }
+
+        on_line(&String::from_utf8_lossy(line));
+    }
+
+    Some(pos)
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct CallTimings {
     pub time_call_start: Option<Instant>,
@@ -751,3 +1032,67 @@ impl<'a> fmt::Debug for FakeRequest<'a> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use crate::transport::time::Duration;
+    use crate::transport::LazyBuffers;
+
+    use super::*;
+
+    #[test]
+    fn bad_status_is_truncated() {
+        let mut input = b"BORKED ".to_vec();
+        input.extend(std::iter::repeat(b'x').take(10_000));
+        input.extend(b"\r\n\r\n");
+
+        let err = bad_status(&input, hoot::Error::MissingResponseVersion);
+        let Error::BadStatus(line) = err else {
+            panic!("expected BadStatus: {:?}", err);
+        };
+        assert!(line.starts_with("BORKED x"));
+        assert_eq!(line.len(), MAX_BAD_STATUS_LEN);
+    }
+
+    #[test]
+    fn trailers_follow_final_chunk() {
+        let request = Request::post("https://my.test/post")
+            .header("transfer-encoding", "chunked")
+            .body(())
+            .unwrap();
+
+        let mut buffers = LazyBuffers::new(1024, 1024);
+
+        let mut flow = Flow::new(request).unwrap().proceed();
+        flow.write(buffers.output_mut()).unwrap();
+        let Some(SendRequestResult::SendBody(mut flow)) = flow.proceed() else {
+            panic!("expected to send body");
+        };
+
+        let mut reader = Cursor::new(b"hello");
+        let mut body = SendBody::from_reader(&mut reader).with_trailers(|| {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+            trailers
+        });
+
+        let timeout = NextTimeout {
+            after: Duration::NotHappening,
+            reason: TimeoutReason::SendBody,
+        };
+
+        let mut sent = vec![];
+        while !flow.can_proceed() {
+            let Event::Transmit { amount, .. } =
+                send_body(&mut flow, &mut buffers, &mut body, timeout).unwrap()
+            else {
+                panic!("expected transmit");
+            };
+            sent.extend_from_slice(&buffers.output()[..amount]);
+        }
+
+        assert_eq!(sent, b"5\r\nhello\r\n0\r\nx-checksum: abc\r\n\r\n");
+    }
+}
@@ -1,7 +1,9 @@
 use core::fmt;
 use std::convert::TryFrom;
 use std::io::{self, ErrorKind};
+use std::time::{Duration, SystemTime};
 
+use http::header::AsHeaderName;
 use http::uri::{Authority, Scheme};
 use http::{HeaderMap, Response, Uri};
 
@@ -295,11 +297,16 @@ impl UriExt for Uri {
 
 pub(crate) trait HeaderMapExt {
     fn get_str(&self, k: &str) -> Option<&str>;
+    /// The items of a comma separated list header, over all its values. Items are
+    /// trimmed and empty ones left out.
+    fn get_list<K: AsHeaderName>(&self, k: K) -> Vec<&str>;
     fn is_chunked(&self) -> bool;
     fn content_length(&self) -> Option<u64>;
     #[cfg(any(feature = "gzip", feature = "brotli"))]
     fn has_accept_encoding(&self) -> bool;
     fn has_user_agent(&self) -> bool;
+    fn has_keep_alive(&self) -> bool;
+    fn has_conflicting_content_length(&self) -> bool;
     fn has_send_body_mode(&self) -> bool {
         self.is_chunked() || self.content_length().is_some()
     }
@@ -310,6 +317,16 @@ impl HeaderMapExt for HeaderMap {
         self.get(k).and_then(|v| v.to_str().ok())
     }
 
+    fn get_list<K: AsHeaderName>(&self, k: K) -> Vec<&str> {
+        self.get_all(k)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
     fn is_chunked(&self) -> bool {
         self.get_str("transfer-encoding")
             .map(|v| v.contains("chunked"))
@@ -330,4 +347,221 @@ impl HeaderMapExt for HeaderMap {
     fn has_user_agent(&self) -> bool {
         self.contains_key("user-agent")
     }
+
+    fn has_keep_alive(&self) -> bool {
+        self.get_list("connection")
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case("keep-alive"))
+    }
+
+    fn has_conflicting_content_length(&self) -> bool {
+        let mut values = self
+            .get_all("content-length")
+            .iter()
+            .map(|v| v.to_str().ok().map(str::trim));
+        let Some(first) = values.next() else {
+            return false;
+        };
+        values.any(|v| v != first)
+    }
+}
+
+/// Appends `value` encoded as per `application/x-www-form-urlencoded`.
+pub(crate) fn form_urlencode(value: &str, out: &mut String) {
+    for b in value.bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                out.push(b as char)
+            }
+            b' ' => out.push('+'),
+            _ => {
+                const HEX: &[u8; 16] = b"0123456789ABCDEF";
+                out.push('%');
+                out.push(HEX[(b >> 4) as usize] as char);
+                out.push(HEX[(b & 0xf) as usize] as char);
+            }
+        }
+    }
+}
+
+/// A random key in the UUID v4 format.
+///
+/// The randomness comes from std's hasher keys. That's unique enough to tell requests
+/// apart, but not suitable for anything secret.
+pub(crate) fn random_key() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let random = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u128(nanos);
+        hasher.finish()
+    };
+
+    let hi = random();
+    // Version 4 and variant 1 bits.
+    let lo = (random() & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        hi >> 32,
+        (hi >> 16) & 0xffff,
+        hi & 0x0fff,
+        lo >> 48,
+        lo & 0xffff_ffff_ffff
+    )
+}
+
+/// Parses a `Content-Type` header value into the mime type and its parameters.
+///
+/// `text/plain; charset="utf-8"` gives `("text/plain", [("charset", "utf-8")])`.
+/// Parameter names are lowercased and quoted values are unquoted. Parameters
+/// without a `=` are skipped.
+pub(crate) fn parse_content_type(value: &str) -> (&str, Vec<(String, String)>) {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (i, ch) in value.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                segments.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&value[start..]);
+
+    let mime_type = segments[0].trim();
+
+    let params = segments[1..]
+        .iter()
+        .filter_map(|segment| {
+            let (name, value) = segment.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some((name.to_ascii_lowercase(), unquote(value.trim())))
+        })
+        .collect();
+
+    (mime_type, params)
+}
+
+/// Removes the quotes of a quoted string and resolves escapes. Other values are as is.
+fn unquote(value: &str) -> String {
+    match parse_quoted(value) {
+        Some((unquoted, len)) if len == value.len() => unquoted,
+        _ => value.to_string(),
+    }
+}
+
+/// Reads the quoted string `s` starts with, resolving escapes.
+///
+/// Returns the value without the quotes and the length of the quoted string in `s`, or
+/// `None` if `s` doesn't start with a quote or the closing quote is missing.
+pub(crate) fn parse_quoted(s: &str) -> Option<(String, usize)> {
+    let inner = s.strip_prefix('"')?;
+
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => return Some((value, i + 2)),
+            '\\' => value.push(chars.next()?.1),
+            _ => value.push(ch),
+        }
+    }
+
+    // No closing quote.
+    None
+}
+
+/// Parses an HTTP date as per RFC 9110 section 5.6.7.
+///
+/// The preferred IMF-fixdate `Sun, 06 Nov 1994 08:49:37 GMT`, as well as the obsolete
+/// RFC 850 `Sunday, 06-Nov-94 08:49:37 GMT` and asctime `Sun Nov  6 08:49:37 1994`.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+
+    let (day, month, year, time) = match parts[..] {
+        [_, day, month, year, time, "GMT"] => (day, month, year.parse().ok()?, time),
+        [_, date, time, "GMT"] => {
+            let mut split = date.split('-');
+            let (day, month, year) = (split.next()?, split.next()?, split.next()?);
+            // Two digit years, as recommended by RFC 9110.
+            let year: i64 = year.parse().ok()?;
+            let year = if year < 70 { 2000 + year } else { 1900 + year };
+            (day, month, year, time)
+        }
+        [_, month, day, time, year] => (day, month, year.parse().ok()?, time),
+        _ => return None,
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+
+    let mut split = time.split(':');
+    let hour: i64 = split.next()?.parse().ok()?;
+    let minute: i64 = split.next()?.parse().ok()?;
+    let second: i64 = split.next()?.parse().ok()?;
+
+    // Limiting the year also keeps the arithmetic below from overflowing.
+    if split.next().is_some()
+        || !(1..=9999).contains(&year)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || !(0..=23).contains(&hour)
+        || !(0..=59).contains(&minute)
+        || !(0..=60).contains(&second)
+    {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+
+    if secs >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
 }